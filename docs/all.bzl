@@ -14,6 +14,10 @@ load("@io_bazel_rules_rust//proto:toolchain.bzl", _rust_proto_toolchain = "rust_
 #     # external/docs/all.bzl:7:1: file '@io_bazel_rules_rust//rust:rust.bzl' does not contain symbol 'rust_benchmark ' (did you mean 'rust_benchmark'?)
 #     # _rust_benchmark = "rust_benchmark ",
 #     _rust_binary = "rust_binary",
+#     _rust_compile_fail_test = "rust_compile_fail_test",
+#     _rust_debug = "rust_debug",
+#     _rust_binary_objcopy = "rust_binary_objcopy",
+#     _rust_reproducibility_test = "rust_reproducibility_test",
 #     _rust_doc = "rust_doc",
 #     _rust_doc_test = "rust_doc_test",
 #     _rust_library = "rust_library",
@@ -28,6 +32,10 @@ load("@io_bazel_rules_rust//proto:toolchain.bzl", _rust_proto_toolchain = "rust_
 # rust_library = _rust_library
 # rust_binary = _rust_binary
 # rust_test = _rust_test
+# rust_compile_fail_test = _rust_compile_fail_test
+# rust_debug = _rust_debug
+# rust_binary_objcopy = _rust_binary_objcopy
+# rust_reproducibility_test = _rust_reproducibility_test
 # rust_doc = _rust_doc
 # rust_doc_test = _rust_doc_test
 #
@@ -0,0 +1,36 @@
+// Copyright 2020 The Bazel Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A benchmark with its own `main`, in the style of criterion, which works on stable Rust.
+
+extern crate fibonacci;
+
+use std::time::Instant;
+
+const ITERATIONS: u32 = 100_000;
+
+fn main() {
+    let start = Instant::now();
+    let mut result = 0;
+    for _ in 0..ITERATIONS {
+        result += fibonacci::fibonacci(40) % 2;
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "fibonacci(40): {} ns/iter ({})",
+        elapsed.as_nanos() / u128::from(ITERATIONS),
+        result
+    );
+}
@@ -41,7 +41,13 @@ load(
     _generate_proto = "rust_generate_proto",
     _generated_file_stem = "generated_file_stem",
 )
-load("@io_bazel_rules_rust//rust:private/rustc.bzl", "CrateInfo", "rustc_compile_action")
+load(
+    "@io_bazel_rules_rust//rust:private/rustc.bzl",
+    "CrateInfo",
+    "determine_crate_name",
+    "determine_output_hash",
+    "rustc_compile_action",
+)
 load("@io_bazel_rules_rust//rust:private/utils.bzl", "find_toolchain")
 load("@io_bazel_rules_rust//rust:private/transitions.bzl", "proc_macro_host_transition")
 
@@ -153,7 +159,7 @@ def _rust_proto_compile(protos, descriptor_sets, imports, crate_name, ctx, grpc,
     srcs.append(lib_rs)
 
     # And simulate rust_library behavior
-    output_hash = repr(hash(lib_rs.path))
+    output_hash = determine_output_hash(ctx)
     rust_lib = ctx.actions.declare_file("%s/lib%s-%s.rlib" % (
         output_dir,
         crate_name,
@@ -172,6 +178,7 @@ def _rust_proto_compile(protos, descriptor_sets, imports, crate_name, ctx, grpc,
             output = rust_lib,
             edition = proto_toolchain.edition,
             rustc_env = {},
+            compile_data = depset([]),
         ),
         output_hash = output_hash,
     )
@@ -191,7 +198,7 @@ def _rust_protogrpc_library_impl(ctx, grpc):
         srcs,
         depset(transitive = [p.transitive_descriptor_sets for p in proto]),
         depset(transitive = [p.transitive_imports for p in proto]),
-        ctx.label.name,
+        determine_crate_name(ctx),
         ctx,
         grpc,
         ctx.attr.rust_deps,
@@ -215,6 +222,12 @@ rust_proto_library = rule(
             providers = [ProtoInfo],
             aspects = [_rust_proto_aspect],
         ),
+        "crate_name": attr.string(
+            doc = """
+                The name of the generated crate. Defaults to the name of the target, with any
+                hyphens replaced by underscores.
+            """,
+        ),
         "rust_deps": attr.label_list(
             doc = "The crates the generated library depends on.",
             default = PROTO_COMPILE_DEPS,
@@ -279,6 +292,12 @@ rust_grpc_library = rule(
             providers = [ProtoInfo],
             aspects = [_rust_proto_aspect],
         ),
+        "crate_name": attr.string(
+            doc = """
+                The name of the generated crate. Defaults to the name of the target, with any
+                hyphens replaced by underscores.
+            """,
+        ),
         "rust_deps": attr.label_list(
             doc = "The crates the generated library depends on.",
             default = GRPC_COMPILE_DEPS,
@@ -5,52 +5,126 @@ load(
     "triple_to_constraint_set",
 )
 
-# All T1 Platforms should be supported, but aren't, see inline notes.
+# All T1 Platforms are supported. The MSVC and GNU ABIs on Windows are told apart by the
+# @io_bazel_rules_rust//rust/platform/abi constraint, which is only set for GNU.
 _SUPPORTED_T1_PLATFORM_TRIPLES = [
     "i686-apple-darwin",
     "i686-pc-windows-gnu",
+    "i686-pc-windows-msvc",
     "i686-unknown-linux-gnu",
     "x86_64-apple-darwin",
     "x86_64-pc-windows-gnu",
+    "x86_64-pc-windows-msvc",
     "x86_64-unknown-linux-gnu",
-    # N.B. These "alternative" envs are not supported, as bazel cannot distinguish between them
-    # and others using existing @platforms// config_values
-    #
-    #"i686-pc-windows-msvc",
-    #"x86_64-pc-windows-msvc",
 ]
 
-# Some T2 Platforms are supported, provided we have mappings to @platforms// entries.
+# All T2 Platforms are supported. Those which @platforms// has no entries for use the constraint
+# values in @io_bazel_rules_rust//rust/platform/{cpu,os,abi}.
 # See @io_bazel_rules_rust//rust/platform:triple_mappings.bzl for the complete list.
 _SUPPORTED_T2_PLATFORM_TRIPLES = [
     "aarch64-apple-ios",
+    "aarch64-fuchsia",
     "aarch64-linux-android",
+    "aarch64-pc-windows-msvc",
     "aarch64-unknown-linux-gnu",
-    "powerpc-unknown-linux-gnu",
+    "aarch64-unknown-linux-musl",
+    "arm-linux-androideabi",
     "arm-unknown-linux-gnueabi",
-    "s390x-unknown-linux-gnu",
+    "arm-unknown-linux-gnueabihf",
+    "arm-unknown-linux-musleabi",
+    "arm-unknown-linux-musleabihf",
+    "armv5te-unknown-linux-gnueabi",
+    "armv7-apple-ios",
+    "armv7-linux-androideabi",
+    "armv7-unknown-linux-gnueabihf",
+    "armv7-unknown-linux-musleabihf",
+    "armv7s-apple-ios",
+    "asmjs-unknown-emscripten",
+    "i386-apple-ios",
+    "i586-pc-windows-msvc",
+    "i586-unknown-linux-gnu",
+    "i586-unknown-linux-musl",
     "i686-linux-android",
     "i686-unknown-freebsd",
+    "i686-unknown-linux-musl",
+    "mips-unknown-linux-gnu",
+    "mips-unknown-linux-musl",
+    "mips64-unknown-linux-gnuabi64",
+    "mips64el-unknown-linux-gnuabi64",
+    "mipsel-unknown-linux-gnu",
+    "mipsel-unknown-linux-musl",
+    "powerpc-unknown-linux-gnu",
+    "powerpc64-unknown-linux-gnu",
+    "powerpc64le-unknown-linux-gnu",
+    "riscv64gc-unknown-linux-gnu",
+    "s390x-unknown-linux-gnu",
+    "sparc64-unknown-linux-gnu",
+    "sparcv9-sun-solaris",
+    "thumbv6m-none-eabi",
+    "thumbv7em-none-eabi",
+    "thumbv7em-none-eabihf",
+    "thumbv7m-none-eabi",
+    "thumbv8m.base-none-eabi",
+    "thumbv8m.main-none-eabi",
+    "thumbv8m.main-none-eabihf",
+    "wasm32-unknown-emscripten",
+    "wasm32-unknown-unknown",
+    "wasm32-wasi",
     "x86_64-apple-ios",
+    "x86_64-fuchsia",
     "x86_64-linux-android",
+    "x86_64-sun-solaris",
     "x86_64-unknown-freebsd",
+    "x86_64-unknown-linux-gnux32",
+    "x86_64-unknown-linux-musl",
+    "x86_64-unknown-netbsd",
+    "x86_64-unknown-redox",
 ]
 
+SUPPORTED_PLATFORM_TRIPLES = _SUPPORTED_T1_PLATFORM_TRIPLES + _SUPPORTED_T2_PLATFORM_TRIPLES
+
 _SUPPORTED_CPU_ARCH = [
     "x86_64",
     "powerpc",
     "aarch64",
     "arm",
+    "armv5te",
+    "armv7",
+    "armv7s",
+    "asmjs",
+    "i586",
     "i686",
+    "mips",
+    "mipsel",
+    "mips64",
+    "mips64el",
+    "powerpc64",
+    "powerpc64le",
+    "riscv64gc",
     "s390x",
+    "sparc64",
+    "thumbv6m",
+    "thumbv7em",
+    "thumbv7m",
+    "thumbv8m.base",
+    "thumbv8m.main",
 ]
 
 _SUPPORTED_SYSTEMS = [
     "android",
+    "dragonfly",
+    "emscripten",
     "freebsd",
+    "fuchsia",
     "darwin",
     "ios",
     "linux",
+    "netbsd",
+    "none",
+    "openbsd",
+    "redox",
+    "solaris",
+    "wasi",
     "windows",
 ]
 
@@ -73,8 +147,7 @@ def declare_config_settings():
         actual = ":darwin",
     )
 
-    all_supported_triples = _SUPPORTED_T1_PLATFORM_TRIPLES + _SUPPORTED_T2_PLATFORM_TRIPLES
-    for triple in all_supported_triples:
+    for triple in SUPPORTED_PLATFORM_TRIPLES:
         native.config_setting(
             name = triple,
             constraint_values = triple_to_constraint_set(triple),
@@ -91,3 +164,37 @@ def declare_config_settings():
             "@io_bazel_rules_rust//rust/platform:wasm32",
         ],
     )
+
+# The Rust release channels platforms can select the toolchains of.
+RUST_CHANNELS = ["stable", "beta", "nightly"]
+
+def declare_platforms():
+    """Declares a platform for each supported triple, named after the triple.
+
+    These can be passed to `--platforms` to build for the triple, eg.
+    `--platforms=@io_bazel_rules_rust//rust/platform/triples:aarch64-unknown-linux-gnu`.
+    """
+    for triple in SUPPORTED_PLATFORM_TRIPLES:
+        native.platform(
+            name = triple,
+            constraint_values = triple_to_constraint_set(triple),
+        )
+
+def declare_channel_platforms():
+    """Declares a variant of the host platform and each triple's platform for each Rust channel.
+
+    These are named `<host or triple>-<channel>`, eg. `host-nightly` or
+    `aarch64-unknown-linux-gnu-beta`, and are switched to by the `channel` attribute of Rust rules.
+    """
+    for channel in RUST_CHANNELS:
+        channel_constraint = "@io_bazel_rules_rust//rust/settings:" + channel
+        native.platform(
+            name = "host-" + channel,
+            parents = ["@local_config_platform//:host"],
+            constraint_values = [channel_constraint],
+        )
+        for triple in SUPPORTED_PLATFORM_TRIPLES:
+            native.platform(
+                name = "{}-{}".format(triple, channel),
+                constraint_values = triple_to_constraint_set(triple) + [channel_constraint],
+            )
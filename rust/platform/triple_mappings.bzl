@@ -4,19 +4,33 @@ _CPU_ARCH_TO_BUILTIN_PLAT_SUFFIX = {
     "powerpc": "ppc",
     "aarch64": "aarch64",
     "arm": "arm",
+    "i386": "x86_32",
     "i686": "x86_32",
     "s390x": "s390x",
-    "asmjs": None,
-    "i386": None,
-    "i586": None,
-    "powerpc64": None,
-    "powerpc64le": None,
-    "armv7": None,
-    "armv7s": None,
-    "s390": None,
-    "le32": None,
-    "mips": None,
-    "mipsel": None,
+}
+
+# CPUs with no "@platforms//cpu entry, which have one in "@io_bazel_rules_rust//rust/platform/cpu.
+# This includes variants of CPUs which do have an entry, so that every triple has its own platform.
+_CPU_ARCH_TO_RULES_RUST_CPU = {
+    "armv5te": "armv5te",
+    "armv7": "armv7",
+    "armv7s": "armv7s",
+    "asmjs": "asmjs",
+    "i586": "i586",
+    "mips": "mips",
+    "mipsel": "mipsel",
+    "mips64": "mips64",
+    "mips64el": "mips64el",
+    "powerpc64": "powerpc64",
+    "powerpc64le": "powerpc64le",
+    "riscv64gc": "riscv64",
+    "sparc64": "sparc64",
+    "sparcv9": "sparc64",
+    "thumbv6m": "thumbv6m",
+    "thumbv7em": "thumbv7em",
+    "thumbv7m": "thumbv7m",
+    "thumbv8m.base": "thumbv8m.base",
+    "thumbv8m.main": "thumbv8m.main",
 }
 
 # Systems that map to a "@platforms//os entry
@@ -27,52 +41,270 @@ _SYSTEM_TO_BUILTIN_SYS_SUFFIX = {
     "windows": "windows",
     "ios": "ios",
     "android": "android",
-    "emscripten": None,
-    "nacl": None,
-    "bitrig": None,
-    "dragonfly": None,
-    "netbsd": None,
-    "openbsd": None,
-    "solaris": None,
+    "androideabi": "android",
+}
+
+# Systems with no "@platforms//os entry, which have one in "@io_bazel_rules_rust//rust/platform/os.
+# Targets of the `unknown` system, such as wasm32-unknown-unknown, have no OS constraint.
+_SYSTEM_TO_RULES_RUST_OS = {
+    "dragonfly": "dragonfly",
+    "emscripten": "emscripten",
+    "fuchsia": "fuchsia",
+    "netbsd": "netbsd",
+    "none": "none",
+    "openbsd": "openbsd",
+    "redox": "redox",
+    "solaris": "solaris",
+    "wasi": "wasi",
 }
 
+# ABIs which have an "@io_bazel_rules_rust//rust/platform/abi entry. Others are the default ABI of
+# their CPU and system, which has no constraint, so that the host platform matches it.
+_RULES_RUST_ABIS = [
+    "eabihf",
+    "gnueabihf",
+    "gnux32",
+    "musl",
+    "musleabi",
+    "musleabihf",
+]
+
 _SYSTEM_TO_BINARY_EXT = {
+    "android": "",
+    "androideabi": "",
+    "dragonfly": "",
     "freebsd": "",
+    "fuchsia": "",
+    "ios": "",
     "linux": "",
+    "netbsd": "",
+    "none": "",
+    "openbsd": "",
+    "redox": "",
+    "solaris": "",
     # TODO(acmcarther): To be verified
     "darwin": "",
     "windows": ".exe",
     "emscripten": ".js",
+    "wasi": ".wasm",
     "unknown": "",
 }
 
 _SYSTEM_TO_STATICLIB_EXT = {
+    "android": ".a",
+    "androideabi": ".a",
+    "dragonfly": ".a",
     "freebsd": ".a",
+    "fuchsia": ".a",
+    "ios": ".a",
     "linux": ".a",
+    "netbsd": ".a",
+    "none": ".a",
+    "openbsd": ".a",
+    "redox": ".a",
+    "solaris": ".a",
     "darwin": ".a",
     # TODO(acmcarther): To be verified
     "windows": ".lib",
     "emscripten": ".js",
+    "wasi": ".a",
     "unknown": "",
 }
 
 _SYSTEM_TO_DYLIB_EXT = {
+    "android": ".so",
+    "androideabi": ".so",
+    "dragonfly": ".so",
     "freebsd": ".so",
+    "fuchsia": ".so",
+    "ios": ".dylib",
     "linux": ".so",
+    "netbsd": ".so",
+    "none": ".so",
+    "openbsd": ".so",
+    "redox": ".so",
+    "solaris": ".so",
     "darwin": ".dylib",
     # TODO(acmcarther): To be verified
     "windows": ".dll",
     "emscripten": ".js",
+    "wasi": ".wasm",
     "unknown": ".wasm",
 }
 
+# The `target_arch` rustc reports for CPUs whose name differs from it
+_CPU_ARCH_TO_TARGET_ARCH = {
+    "armv5te": "arm",
+    "armv7": "arm",
+    "armv7s": "arm",
+    "i386": "x86",
+    "i586": "x86",
+    "i686": "x86",
+    "mipsel": "mips",
+    "mips64el": "mips64",
+    "powerpc64le": "powerpc64",
+    "riscv64gc": "riscv64",
+    "sparcv9": "sparc64",
+    "thumbv6m": "arm",
+    "thumbv7em": "arm",
+    "thumbv7m": "arm",
+    "thumbv8m.base": "arm",
+    "thumbv8m.main": "arm",
+}
+
+# CPUs whose byte order is big-endian, which is otherwise little-endian
+_BIG_ENDIAN_CPU_ARCHS = [
+    "powerpc",
+    "powerpc64",
+    "s390x",
+    "mips",
+    "mips64",
+    "sparc64",
+    "sparcv9",
+]
+
+# CPUs whose pointers are 64 bits wide, which are otherwise 32 bits wide
+_64_BIT_CPU_ARCHS = [
+    "x86_64",
+    "aarch64",
+    "powerpc64",
+    "powerpc64le",
+    "riscv64gc",
+    "s390x",
+    "mips64",
+    "mips64el",
+    "sparc64",
+    "sparcv9",
+]
+
+# The `target_os` rustc reports for systems whose name differs from it
+_SYSTEM_TO_TARGET_OS = {
+    "darwin": "macos",
+    "androideabi": "android",
+}
+
+# Systems in the `unix` family, which have a `target_family`
+_UNIX_SYSTEMS = [
+    "android",
+    "androideabi",
+    "darwin",
+    "dragonfly",
+    "emscripten",
+    "freebsd",
+    "fuchsia",
+    "ios",
+    "linux",
+    "netbsd",
+    "openbsd",
+    "redox",
+    "solaris",
+]
+
+# The target features enabled by default for CPUs, as reported by rustc in `target_feature`
+_CPU_ARCH_TO_DEFAULT_FEATURES = {
+    "x86_64": ["fxsr", "sse", "sse2"],
+    "i686": ["fxsr", "sse", "sse2"],
+}
+
+def _split_triple(triple):
+    """Splits a triple into its CPU, vendor, system and ABI.
+
+    Some triples have no vendor, eg. `x86_64-fuchsia` or `thumbv7m-none-eabi`, or no ABI, eg.
+    `x86_64-apple-darwin`, in which case those are `unknown` and empty respectively.
+    """
+    component_parts = triple.split("-")
+    if len(component_parts) == 2:
+        return struct(
+            cpu_arch = component_parts[0],
+            vendor = "unknown",
+            system = component_parts[1],
+            abi = "",
+        )
+    if len(component_parts) == 3 and component_parts[1] == "none":
+        return struct(
+            cpu_arch = component_parts[0],
+            vendor = "unknown",
+            system = "none",
+            abi = component_parts[2],
+        )
+    if len(component_parts) < 3:
+        fail("Expected target triple to contain at least two sections separated by '-'")
+
+    return struct(
+        cpu_arch = component_parts[0],
+        vendor = component_parts[1],
+        system = component_parts[2],
+        abi = component_parts[3] if len(component_parts) == 4 else "",
+    )
+
+def triple_to_cfg(triple):
+    """Returns the `target_*` configuration options rustc sets for a target triple.
+
+    Args:
+        triple (str): A platform triple, eg. `x86_64-unknown-linux-gnu`.
+
+    Returns:
+        dict: The values of `target_arch`, `target_os`, `target_family`, `target_env`,
+            `target_vendor`, `target_endian`, `target_pointer_width` and `target_feature`, the
+            last of which is comma-separated. Options rustc doesn't set are empty.
+    """
+    parts = _split_triple(triple)
+    cpu_arch = parts.cpu_arch
+    system = parts.system
+    abi = parts.abi
+
+    # Android triples, eg. `aarch64-linux-android`, have no vendor.
+    vendor = "unknown" if parts.vendor == "linux" else parts.vendor
+
+    if system == "windows":
+        family = "windows"
+    elif system in _UNIX_SYSTEMS:
+        family = "unix"
+    else:
+        family = ""
+
+    env = ""
+    for libc in ["gnu", "musl", "msvc", "uclibc"]:
+        if abi.startswith(libc):
+            env = libc
+
+    features = list(_CPU_ARCH_TO_DEFAULT_FEATURES.get(cpu_arch, []))
+
+    # rustc targets the Core 2 on macOS, which also has SSSE3.
+    if cpu_arch == "x86_64" and system == "darwin":
+        features += ["sse3", "ssse3"]
+
+    # musl targets link the C runtime statically by default.
+    if env == "musl":
+        features.append("crt-static")
+
+    # The x32 ABI uses 32 bit pointers on x86_64.
+    pointer_width = "64" if cpu_arch in _64_BIT_CPU_ARCHS and abi != "gnux32" else "32"
+
+    return {
+        "target_arch": _CPU_ARCH_TO_TARGET_ARCH.get(cpu_arch, cpu_arch),
+        "target_os": _SYSTEM_TO_TARGET_OS.get(system, system),
+        "target_family": family,
+        "target_env": env,
+        "target_vendor": vendor,
+        "target_endian": "big" if cpu_arch in _BIG_ENDIAN_CPU_ARCHS else "little",
+        "target_pointer_width": pointer_width,
+        "target_feature": ",".join(sorted(features)),
+    }
+
 def cpu_arch_to_constraints(cpu_arch):
-    plat_suffix = _CPU_ARCH_TO_BUILTIN_PLAT_SUFFIX[cpu_arch]
+    if cpu_arch == "wasm32":
+        return ["@io_bazel_rules_rust//rust/platform:wasm32"]
 
-    if not plat_suffix:
-        fail("CPU architecture \"{}\" is not supported by rules_rust".format(cpu_arch))
+    plat_suffix = _CPU_ARCH_TO_BUILTIN_PLAT_SUFFIX.get(cpu_arch)
+    if plat_suffix:
+        return ["@platforms//cpu:{}".format(plat_suffix)]
 
-    return ["@platforms//cpu:{}".format(plat_suffix)]
+    rules_rust_cpu = _CPU_ARCH_TO_RULES_RUST_CPU.get(cpu_arch)
+    if rules_rust_cpu:
+        return ["@io_bazel_rules_rust//rust/platform/cpu:{}".format(rules_rust_cpu)]
+
+    fail("CPU architecture \"{}\" is not supported by rules_rust".format(cpu_arch))
 
 def vendor_to_constraints(vendor):
     # TODO(acmcarther): Review:
@@ -82,24 +314,27 @@ def vendor_to_constraints(vendor):
     return []
 
 def system_to_constraints(system):
-    sys_suffix = _SYSTEM_TO_BUILTIN_SYS_SUFFIX[system]
+    if system == "unknown":
+        return []
+
+    sys_suffix = _SYSTEM_TO_BUILTIN_SYS_SUFFIX.get(system)
+    if sys_suffix:
+        return ["@platforms//os:{}".format(sys_suffix)]
 
-    if not sys_suffix:
-        fail("System \"{}\" is not supported by rules_rust".format(sys_suffix))
+    rules_rust_os = _SYSTEM_TO_RULES_RUST_OS.get(system)
+    if rules_rust_os:
+        return ["@io_bazel_rules_rust//rust/platform/os:{}".format(rules_rust_os)]
 
-    return ["@platforms//os:{}".format(sys_suffix)]
+    fail("System \"{}\" is not supported by rules_rust".format(system))
 
-def abi_to_constraints(abi):
-    # TODO(acmcarther): Implement when C++ toolchain is more mature and we
-    # figure out how they're doing this
+def abi_to_constraints(abi, system = None):
+    # The GNU ABI is the default on most systems, but MSVC is the default on Windows.
+    if abi in _RULES_RUST_ABIS or (system == "windows" and abi == "gnu"):
+        return ["@io_bazel_rules_rust//rust/platform/abi:{}".format(abi)]
     return []
 
 def triple_to_system(triple):
-    component_parts = triple.split("-")
-    if len(component_parts) < 3:
-        fail("Expected target triple to contain at least three sections separated by '-'")
-
-    return component_parts[2]
+    return _split_triple(triple).system
 
 def system_to_dylib_ext(system):
     return _SYSTEM_TO_DYLIB_EXT[system]
@@ -110,26 +345,38 @@ def system_to_staticlib_ext(system):
 def system_to_binary_ext(system):
     return _SYSTEM_TO_BINARY_EXT[system]
 
-def triple_to_constraint_set(triple):
-    component_parts = triple.split("-")
-    if len(component_parts) < 3:
-        fail("Expected target triple to contain at least three sections separated by '-'")
+def system_to_target_os(system):
+    """Returns the `target_os` rustc reports for a system, eg. `macos` for `darwin`."""
+    return _SYSTEM_TO_TARGET_OS.get(system, system)
 
-    cpu_arch = component_parts[0]
-    vendor = component_parts[1]
-    system = component_parts[2]
-    abi = None
+def triple_to_exec_constraint_set(triple):
+    """Returns the constraints of the platforms which can run binaries built for `triple`.
 
-    if len(component_parts) == 4:
-        abi = component_parts[3]
+    Unlike those of its target platform, these leave out the ABI, which platforms don't describe
+    unless they are declared for a triple, as the host platform isn't.
+    """
+    parts = _split_triple(triple)
+    return cpu_arch_to_constraints(parts.cpu_arch) + system_to_constraints(parts.system)
 
-    if cpu_arch == "wasm32":
-        return ["@io_bazel_rules_rust//rust/platform:wasm32"]
+def triple_to_toolchain_target_constraint_set(target_triple, exec_triple):
+    """Returns the constraints of the platforms a toolchain for `target_triple` builds for.
+
+    These are those of `target_triple`, except that a windows-gnu toolchain which builds for the
+    triple it runs on isn't constrained to the GNU ABI. The host platform Bazel detects on Windows
+    has no ABI, so that toolchain would otherwise not be used to build for the host.
+    """
+    parts = _split_triple(target_triple)
+    if target_triple == exec_triple and parts.system == "windows" and parts.abi == "gnu":
+        return triple_to_exec_constraint_set(target_triple)
+    return triple_to_constraint_set(target_triple)
+
+def triple_to_constraint_set(triple):
+    parts = _split_triple(triple)
 
     constraint_set = []
-    constraint_set += cpu_arch_to_constraints(cpu_arch)
-    constraint_set += vendor_to_constraints(vendor)
-    constraint_set += system_to_constraints(system)
-    constraint_set += abi_to_constraints(abi)
+    constraint_set += cpu_arch_to_constraints(parts.cpu_arch)
+    constraint_set += vendor_to_constraints(parts.vendor)
+    constraint_set += system_to_constraints(parts.system)
+    constraint_set += abi_to_constraints(parts.abi, parts.system)
 
     return constraint_set
@@ -0,0 +1,185 @@
+# Copyright 2020 The Bazel Authors. All rights reserved.
+#
+# Licensed under the Apache License, Version 2.0 (the "License");
+# you may not use this file except in compliance with the License.
+# You may obtain a copy of the License at
+#
+#    http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+
+load("@bazel_skylib//lib:shell.bzl", "shell")
+load(
+    "@io_bazel_rules_rust//rust:private/rustc.bzl",
+    "CrateInfo",
+    "REDACTED_BIN_DIR",
+    "REDACTED_EXTERNAL",
+    "REDACTED_PWD",
+)
+load("@io_bazel_rules_rust//rust:private/utils.bzl", "find_toolchain")
+
+_SCRIPT = """#!/usr/bin/env bash
+set -euo pipefail
+
+# Prints the physical path of a file, following symlinks.
+resolve() {{
+  local path="$1" link
+  while [[ -L "$path" ]]; do
+    link="$(readlink "$path")"
+    if [[ "$link" == /* ]]; then
+      path="$link"
+    else
+      path="$(dirname "$path")/$link"
+    fi
+  done
+  echo "$(cd "$(dirname "$path")" && pwd -P)/$(basename "$path")"
+}}
+
+# Quotes an argument of a debugger command, which gdb and lldb would otherwise split at spaces.
+quote() {{
+  local arg="${{1//\\\\/\\\\\\\\}}"
+  echo "\\"${{arg//\\"/\\\\\\"}}\\""
+}}
+
+binary="$(pwd)/{binary}"
+debugger="$(pwd)/{debugger}"
+
+# rust-gdb and rust-lldb find the pretty printers for std types in the sysroot of $RUSTC, or of the
+# rustc on the PATH for older versions, which must be that of the toolchain the binary was built with.
+export RUSTC="$(pwd)/{rustc}"
+export PATH="$(dirname "$RUSTC"):$PATH"
+
+# The binary in the runfiles links back to the execution root it was built in.
+execroot="$(resolve "$binary")"
+execroot="${{execroot%/{bin_dir}/{bin_relative_binary}}}"
+workspace="${{BUILD_WORKSPACE_DIRECTORY:-$execroot}}"
+
+# rustc was told to remap these prefixes, to make its outputs reproducible. Relative paths are
+# recorded relative to the redacted execution root, so the prefixes are also mapped below it. The
+# debuggers apply the first matching substitution.
+substitutions=(
+  "{redacted_pwd}/{redacted_external}" "$execroot/external"
+  "{redacted_pwd}/{redacted_bin_dir}" "$execroot/{bin_dir}"
+  "{redacted_pwd}" "$workspace"
+  "{redacted_external}" "$execroot/external"
+  "{redacted_bin_dir}" "$execroot/{bin_dir}"
+)
+
+case "{debugger_kind}" in
+  gdb)
+    commands=()
+    for ((i = 0; i < ${{#substitutions[@]}}; i += 2)); do
+      commands+=(-iex "set substitute-path $(quote "${{substitutions[i]}}") $(quote "${{substitutions[i + 1]}}")")
+    done
+    exec "$debugger" "${{commands[@]}}" --args "$binary" "$@"
+    ;;
+  lldb)
+    source_map="settings set target.source-map"
+    for substitution in "${{substitutions[@]}}"; do
+      source_map+=" $(quote "$substitution")"
+    done
+    exec "$debugger" -O "$source_map" -- "$binary" "$@"
+    ;;
+esac
+"""
+
+def _bin_relative_path(f):
+    """Returns the path of a generated file relative to the bin directory it is in."""
+    if f.short_path.startswith("../"):
+        return "external/" + f.short_path[len("../"):]
+    return f.short_path
+
+def _rust_debug_impl(ctx):
+    toolchain = find_toolchain(ctx)
+    debugger = toolchain.rust_gdb if ctx.attr.debugger == "gdb" else toolchain.rust_lldb
+    if not debugger:
+        fail("The Rust toolchain has no rust-{}".format(ctx.attr.debugger), "debugger")
+
+    # The executable of a rust_test is the test launcher, so debug the crate it runs instead.
+    binary = ctx.attr.target[CrateInfo].output
+    if binary.is_source:
+        fail("{} doesn't build a binary".format(ctx.attr.target.label), "target")
+
+    ctx.actions.write(
+        output = ctx.outputs.executable,
+        content = _SCRIPT.format(
+            binary = binary.short_path,
+            bin_relative_binary = _bin_relative_path(binary),
+            bin_dir = binary.root.path,
+            debugger = debugger.short_path,
+            debugger_kind = ctx.attr.debugger,
+            rustc = toolchain.rustc.short_path,
+            redacted_pwd = REDACTED_PWD,
+            redacted_external = REDACTED_EXTERNAL,
+            redacted_bin_dir = REDACTED_BIN_DIR,
+        ),
+        is_executable = True,
+    )
+
+    runfiles = ctx.runfiles(
+        files = [binary, debugger, toolchain.rustc],
+        transitive_files = depset(
+            toolchain.rust_debugger_scripts,
+            transitive = [toolchain.rustc_lib.files],
+        ),
+    )
+    runfiles = runfiles.merge(ctx.attr.target[DefaultInfo].default_runfiles)
+    return [
+        DefaultInfo(
+            runfiles = runfiles,
+            executable = ctx.outputs.executable,
+        ),
+    ]
+
+rust_debug = rule(
+    _rust_debug_impl,
+    attrs = {
+        "target": attr.label(
+            doc = "The `rust_binary` or `rust_test` to debug.",
+            mandatory = True,
+            providers = [CrateInfo],
+        ),
+        "debugger": attr.string(
+            doc = "The debugger to run: `gdb` or `lldb`.",
+            default = "gdb",
+            values = ["gdb", "lldb"],
+        ),
+    },
+    executable = True,
+    toolchains = ["@io_bazel_rules_rust//rust:toolchain"],
+    doc = """
+Runs a Rust binary or test under a debugger, with `bazel run`.
+
+rustc replaces the paths of the execution root, external repositories and
+generated files in the debuginfo it writes, so that builds are reproducible.
+This runs the `rust-gdb` or `rust-lldb` of the toolchain the binary was built
+with, which load the pretty printers for std types, and tells the debugger how
+to map those paths back to the workspace, so that it finds the sources.
+
+Example:
+
+```python
+load("@io_bazel_rules_rust//rust:rust.bzl", "rust_binary", "rust_debug")
+
+rust_binary(
+    name = "hello_world",
+    srcs = ["src/main.rs"],
+)
+
+rust_debug(
+    name = "hello_world_debug",
+    target = ":hello_world",
+)
+```
+
+Debug the binary, passing it any arguments after `--`:
+
+```
+bazel run --compilation_mode=dbg //hello_world:hello_world_debug -- --some-flag
+```
+""",
+)
@@ -0,0 +1,114 @@
+# Copyright 2020 The Bazel Authors. All rights reserved.
+#
+# Licensed under the Apache License, Version 2.0 (the "License");
+# you may not use this file except in compliance with the License.
+# You may obtain a copy of the License at
+#
+#    http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+
+load("@io_bazel_rules_rust//rust:private/rustc.bzl", "CrateInfo")
+load("@io_bazel_rules_rust//rust:private/transitions.bzl", "platform_transition")
+load("@io_bazel_rules_rust//rust:private/utils.bzl", "find_toolchain")
+
+def _objcopy(ctx, llvm_objcopy, elf, output_format, extension):
+    """Converts `elf` into an image of `output_format`, named after the target."""
+    image = ctx.actions.declare_file(ctx.label.name + extension)
+    ctx.actions.run(
+        executable = llvm_objcopy,
+        inputs = [elf],
+        outputs = [image],
+        arguments = ["--output-target=" + output_format, elf.path, image.path],
+        mnemonic = "RustObjcopy",
+        progress_message = "Converting {} to {}".format(ctx.attr.binary.label, image.basename),
+    )
+    return image
+
+def _rust_binary_objcopy_impl(ctx):
+    toolchain = find_toolchain(ctx)
+    if not toolchain.llvm_objcopy or not toolchain.llvm_size:
+        fail("The Rust toolchain has no llvm-objcopy and llvm-size. They are in the llvm-tools-preview component.")
+
+    elf = ctx.attr.binary[CrateInfo].output
+    if elf.is_source:
+        fail("{} doesn't build a binary".format(ctx.attr.binary.label), "binary")
+
+    bin_image = _objcopy(ctx, toolchain.llvm_objcopy, elf, "binary", ".bin")
+    hex_image = _objcopy(ctx, toolchain.llvm_objcopy, elf, "ihex", ".hex")
+
+    size_report = ctx.actions.declare_file(ctx.label.name + ".size.txt")
+    ctx.actions.run_shell(
+        command = '"$1" -A -x "$2" > "$3"',
+        arguments = [toolchain.llvm_size.path, elf.path, size_report.path],
+        tools = [toolchain.llvm_size],
+        inputs = [elf],
+        outputs = [size_report],
+        mnemonic = "RustSize",
+        progress_message = "Measuring the sections of {}".format(ctx.attr.binary.label),
+    )
+
+    return [
+        DefaultInfo(files = depset([bin_image, hex_image, size_report])),
+        OutputGroupInfo(
+            bin = depset([bin_image]),
+            hex = depset([hex_image]),
+            size = depset([size_report]),
+        ),
+    ]
+
+rust_binary_objcopy = rule(
+    _rust_binary_objcopy_impl,
+    attrs = {
+        "binary": attr.label(
+            doc = "The `rust_binary` to convert.",
+            mandatory = True,
+            providers = [CrateInfo],
+        ),
+        "platform": attr.label(
+            doc = "The platform to build `binary` for, such as one of `@io_bazel_rules_rust//rust/platform/triples`, instead of that given by `--platforms`.",
+        ),
+        "_whitelist_function_transition": attr.label(
+            default = "//tools/whitelists/function_transition_whitelist",
+        ),
+    },
+    cfg = platform_transition,
+    toolchains = ["@io_bazel_rules_rust//rust:toolchain"],
+    doc = """
+Converts the ELF file of a Rust binary into the images flashed onto embedded devices.
+
+This uses the `llvm-objcopy` and `llvm-size` of the Rust toolchain, which are included with the
+toolchains `rust_repository_set` declares for bare-metal targets. It outputs:
+
+- `<name>.bin`: a raw binary image of the loadable sections, in the `bin` output group.
+- `<name>.hex`: an Intel HEX image, in the `hex` output group.
+- `<name>.size.txt`: the size and address of each section, as `llvm-size -A` reports them, in the
+  `size` output group.
+
+Example:
+
+```python
+load("@io_bazel_rules_rust//rust:rust.bzl", "rust_binary", "rust_binary_objcopy")
+
+rust_binary(
+    name = "firmware",
+    srcs = ["src/main.rs"],
+    # Includes memory.x, which is next to it.
+    linker_script = "link.x",
+    compile_data = ["memory.x"],
+    # The firmware can't be built for the host.
+    tags = ["manual"],
+)
+
+rust_binary_objcopy(
+    name = "firmware_images",
+    binary = ":firmware",
+    platform = "@io_bazel_rules_rust//rust/platform/triples:thumbv7em-none-eabihf",
+)
+```
+""",
+)
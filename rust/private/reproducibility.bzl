@@ -0,0 +1,192 @@
+# Copyright 2020 The Bazel Authors. All rights reserved.
+#
+# Licensed under the Apache License, Version 2.0 (the "License");
+# you may not use this file except in compliance with the License.
+# You may obtain a copy of the License at
+#
+#    http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+
+load("@bazel_skylib//lib:shell.bzl", "shell")
+load("@io_bazel_rules_rust//rust:private/rustc.bzl", "CrateInfo")
+
+_SCRIPT = """#!/usr/bin/env bash
+set -euo pipefail
+
+bazel="${{BAZEL:-{bazel}}}"
+target={target}
+build_flags=({build_flags})
+
+# The outputs of the target, relative to bazel-bin.
+outputs=({outputs})
+
+# Prints the physical path of a file, following symlinks.
+resolve() {{
+  local path="$1" link
+  while [[ -L "$path" ]]; do
+    link="$(readlink "$path")"
+    if [[ "$link" == /* ]]; then
+      path="$link"
+    else
+      path="$(dirname "$path")/$link"
+    fi
+  done
+  echo "$(cd "$(dirname "$path")" && pwd -P)/$(basename "$path")"
+}}
+
+# Under `bazel run`, the workspace is given. Under `bazel test`, it's found by following the
+# runfiles symlink of one of the target's sources back to it.
+workspace="${{BUILD_WORKSPACE_DIRECTORY:-}}"
+marker={marker}
+if [[ -z "$workspace" && -n "$marker" ]]; then
+  marker_path="$(resolve "$marker")"
+  workspace="${{marker_path%/$marker}}"
+fi
+if [[ -z "$workspace" || ! -d "$workspace" ]]; then
+  echo "Can't find the workspace of $target; run this test with 'bazel run'." >&2
+  exit 1
+fi
+
+tmp="${{TEST_TMPDIR:-$(mktemp -d)}}"
+user_root="$tmp/user_root"
+
+# Output bases of different lengths, so that paths leaking into the outputs change their layout as
+# well as their contents.
+output_bases=("$tmp/first" "$tmp/a_differently_named_output_base")
+
+shutdown() {{
+  for output_base in "${{output_bases[@]}}"; do
+    "$bazel" --output_user_root="$user_root" --output_base="$output_base" shutdown || true
+  done
+}}
+trap shutdown EXIT
+
+bin_dirs=()
+cd "$workspace"
+for output_base in "${{output_bases[@]}}"; do
+  startup_flags=(--output_user_root="$user_root" --output_base="$output_base")
+  "$bazel" "${{startup_flags[@]}}" build "${{build_flags[@]}}" -- "$target"
+  bin_dirs+=("$("$bazel" "${{startup_flags[@]}}" info "${{build_flags[@]}}" bazel-bin)")
+done
+
+failed=0
+for output in "${{outputs[@]}}"; do
+  if cmp "${{bin_dirs[0]}}/$output" "${{bin_dirs[1]}}/$output"; then
+    echo "PASS: $output"
+  else
+    echo "FAIL: $output differs between output bases" >&2
+    failed=1
+  fi
+done
+
+if [[ "$failed" != 0 ]]; then
+  echo >&2
+  echo "Compare the outputs with eg. diffoscope to find the cause:" >&2
+  echo "  ${{bin_dirs[0]}}" >&2
+  echo "  ${{bin_dirs[1]}}" >&2
+fi
+exit "$failed"
+"""
+
+def _bin_relative_path(f):
+    """Returns the path of a generated file relative to bazel-bin."""
+    if f.short_path.startswith("../"):
+        return "external/" + f.short_path[len("../"):]
+    return f.short_path
+
+def _rust_reproducibility_test_impl(ctx):
+    target = ctx.attr.target
+    outputs = [f for f in target[DefaultInfo].files.to_list() if not f.is_source]
+    if not outputs:
+        fail("{} has no outputs to compare".format(target.label), "target")
+
+    # A source file of the target in the main workspace, which leads back to it from the runfiles.
+    marker = None
+    if CrateInfo in target:
+        for src in target[CrateInfo].srcs:
+            if src.is_source and not src.owner.workspace_root:
+                marker = src
+                break
+
+    ctx.actions.write(
+        output = ctx.outputs.executable,
+        content = _SCRIPT.format(
+            bazel = ctx.attr.bazel,
+            target = shell.quote(str(target.label)),
+            build_flags = " ".join([shell.quote(flag) for flag in ctx.attr.build_flags]),
+            outputs = " ".join([shell.quote(_bin_relative_path(f)) for f in outputs]),
+            marker = shell.quote(marker.short_path) if marker else "''",
+        ),
+        is_executable = True,
+    )
+
+    return [
+        DefaultInfo(
+            runfiles = ctx.runfiles(files = [marker] if marker else []),
+            executable = ctx.outputs.executable,
+        ),
+    ]
+
+_rust_reproducibility_test = rule(
+    _rust_reproducibility_test_impl,
+    attrs = {
+        "target": attr.label(
+            doc = "The target whose outputs must be reproducible.",
+            mandatory = True,
+        ),
+        "build_flags": attr.string_list(
+            doc = "Flags passed to both builds of `target`, eg. `--compilation_mode=opt`.",
+        ),
+        "bazel": attr.string(
+            doc = "The Bazel binary to build `target` with. Overridden by the `BAZEL` environment variable.",
+            default = "bazel",
+        ),
+    },
+    test = True,
+)
+
+def rust_reproducibility_test(name, tags = [], **kwargs):
+    """Tests that the outputs of a target don't depend on where it is built.
+
+    The target is built twice, in two differently named output bases, and the outputs of both
+    builds are compared byte for byte. Absolute paths, such as those of the execution root or of
+    external repositories, leaking into debuginfo, panic messages or `file!()` make them differ.
+
+    As this runs Bazel, the test is tagged `local`, to run outside of the sandbox, and `manual`, to
+    be left out of wildcards such as `//...`. It needs `bazel` on the `PATH`, which can be passed
+    through with `--test_env=PATH`:
+
+    ```python
+    load("@io_bazel_rules_rust//rust:rust.bzl", "rust_binary", "rust_reproducibility_test")
+
+    rust_binary(
+        name = "hello_world",
+        srcs = ["src/main.rs"],
+    )
+
+    rust_reproducibility_test(
+        name = "hello_world_reproducibility_test",
+        target = ":hello_world",
+        build_flags = ["--compilation_mode=dbg"],
+    )
+    ```
+
+    ```
+    bazel test --test_env=PATH //hello_world:hello_world_reproducibility_test
+    ```
+
+    Args:
+        name: A unique name for this target.
+        tags: Tags for the test, to which `local`, `manual` and `external` are added.
+        **kwargs: The attributes of the test: `target`, `build_flags` and `bazel`.
+    """
+    _rust_reproducibility_test(
+        name = name,
+        tags = tags + [tag for tag in ["local", "manual", "external"] if tag not in tags],
+        **kwargs
+    )
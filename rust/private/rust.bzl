@@ -12,9 +12,22 @@
 # See the License for the specific language governing permissions and
 # limitations under the License.
 
-load("@io_bazel_rules_rust//rust:private/rustc.bzl", "CrateInfo", "rustc_compile_action")
+load("@bazel_skylib//lib:paths.bzl", "paths")
+load("@bazel_skylib//lib:shell.bzl", "shell")
+load(
+    "@io_bazel_rules_rust//rust:private/rustc.bzl",
+    "CrateInfo",
+    "REDACTED_PWD",
+    "construct_rustc_invocation",
+    "determine_crate_name",
+    "determine_debug_info_mode",
+    "determine_output_hash",
+    "get_compile_data_targets",
+    "remap_path",
+    "rustc_compile_action",
+)
 load("@io_bazel_rules_rust//rust:private/utils.bzl", "find_toolchain")
-load("@io_bazel_rules_rust//rust:private/transitions.bzl", "proc_macro_host_transition")
+load("@io_bazel_rules_rust//rust:private/transitions.bzl", "channel_transition", "proc_macro_host_transition")
 
 _OLD_INLINE_TEST_CRATE_MSG = """
 --------------------------------------------------------------------------------
@@ -46,9 +59,6 @@ See https://github.com/bazelbuild/rules_rust/pull/203 for more details
 
 # TODO(marco): Separate each rule into its own file.
 
-def _determine_output_hash(lib_rs):
-    return repr(hash(lib_rs.path))
-
 def _determine_lib_name(name, crate_type, toolchain, lib_hash = ""):
     extension = None
     if crate_type in ("dylib", "cdylib", "proc-macro"):
@@ -72,6 +82,12 @@ def _determine_lib_name(name, crate_type, toolchain, lib_hash = ""):
         extension = extension,
     )
 
+def _compile_data(ctx):
+    return depset(transitive = [
+        target[DefaultInfo].files
+        for target in get_compile_data_targets(ctx)
+    ])
+
 def _get_edition(ctx, toolchain):
     if getattr(ctx.attr, "edition"):
         return ctx.attr.edition
@@ -111,9 +127,9 @@ def _rust_library_impl(ctx):
     toolchain = find_toolchain(ctx)
 
     # Determine unique hash for this rlib
-    output_hash = _determine_output_hash(lib_rs)
+    output_hash = determine_output_hash(ctx)
 
-    crate_name = ctx.label.name.replace("-", "_")
+    crate_name = determine_crate_name(ctx)
     rust_lib_name = _determine_lib_name(
         crate_name,
         ctx.attr.crate_type,
@@ -135,20 +151,37 @@ def _rust_library_impl(ctx):
             output = rust_lib,
             edition = _get_edition(ctx, toolchain),
             rustc_env = ctx.attr.rustc_env,
+            compile_data = _compile_data(ctx),
         ),
         output_hash = output_hash,
     )
 
 def _rust_binary_impl(ctx):
     toolchain = find_toolchain(ctx)
-    crate_name = ctx.label.name.replace("-", "_")
+    crate_name = determine_crate_name(ctx)
 
     if (toolchain.target_arch == "wasm32"):
-        output = ctx.actions.declare_file(crate_name + ".wasm")
+        output_name = crate_name + ".wasm"
     else:
-        output = ctx.actions.declare_file(crate_name)
+        output_name = crate_name
 
-    return rustc_compile_action(
+    # With a runtime environment, the target's executable is a script which sets it up before
+    # running the binary, so the binary is placed in its own directory to keep its name.
+    if ctx.attr.env:
+        output = ctx.actions.declare_file("{}.env/{}".format(ctx.label.name, output_name))
+    else:
+        output = ctx.actions.declare_file(output_name)
+
+    # When its debug info is split off, the binary rustc links keeps it, and is what `CrateInfo`
+    # refers to, so that it can still be debugged with `rust_debug`.
+    if determine_debug_info_mode(ctx, toolchain) == "split":
+        binary = ctx.actions.declare_file("{}.unstripped/{}".format(ctx.label.name, output_name))
+        stripped_output = output
+    else:
+        binary = output
+        stripped_output = None
+
+    providers = rustc_compile_action(
         ctx = ctx,
         toolchain = toolchain,
         crate_info = CrateInfo(
@@ -158,11 +191,16 @@ def _rust_binary_impl(ctx):
             srcs = ctx.files.srcs,
             deps = ctx.attr.deps,
             aliases = ctx.attr.aliases,
-            output = output,
+            output = binary,
             edition = _get_edition(ctx, toolchain),
             rustc_env = ctx.attr.rustc_env,
+            compile_data = _compile_data(ctx),
         ),
+        stripped_output = stripped_output,
     )
+    if ctx.attr.env:
+        return _wrap_with_launcher(ctx, output, providers, [output.short_path], ctx.runfiles())
+    return providers
 
 def _rust_test_common(ctx, test_binary):
     """
@@ -173,8 +211,23 @@ def _rust_test_common(ctx, test_binary):
         test_binary: The File object for the test binary.
     """
     toolchain = find_toolchain(ctx)
+    use_libtest_harness = ctx.attr.use_libtest_harness
+
+    # Like Cargo, tell integration tests where to find the binaries they run. Their paths are
+    # relative to the root of the runfiles, the test's working directory, rather than absolute.
+    rustc_env = dict(ctx.attr.rustc_env)
+    binaries_runfiles = ctx.runfiles()
+    for binary in ctx.attr.binaries:
+        executable = binary[DefaultInfo].files_to_run.executable
+        rustc_env["CARGO_BIN_EXE_" + binary.label.name] = executable.short_path
+        binaries_runfiles = binaries_runfiles.merge(ctx.runfiles(files = [executable]))
+        binaries_runfiles = binaries_runfiles.merge(binary[DefaultInfo].default_runfiles)
 
     if ctx.attr.crate:
+        if not use_libtest_harness:
+            fail("use_libtest_harness = False can't be combined with crate, as the crate's " +
+                 "inline tests need libtest to run.", "use_libtest_harness")
+
         # Target is building the crate in `test` config
         # Build the test binary using the dependency's srcs.
         crate = ctx.attr.crate[CrateInfo]
@@ -187,7 +240,8 @@ def _rust_test_common(ctx, test_binary):
             aliases = ctx.attr.aliases,
             output = test_binary,
             edition = crate.edition,
-            rustc_env = ctx.attr.rustc_env,
+            rustc_env = rustc_env,
+            compile_data = depset(transitive = [crate.compile_data, _compile_data(ctx)]),
         )
     elif len(ctx.attr.deps) == 1 and len(ctx.files.srcs) == 0:
         dep = ctx.attr.deps[0].label
@@ -197,56 +251,314 @@ def _rust_test_common(ctx, test_binary):
         )
         fail(msg)
     else:
-        # Target is a standalone crate. Build the test binary as its own crate.
+        # Target is a standalone crate. Build the test binary as its own crate. Without the libtest
+        # harness, the crate provides its own `main` and is built like any other binary.
         target = CrateInfo(
             name = test_binary.basename,
-            type = "lib",
-            root = _crate_root_src(ctx),
+            type = "lib" if use_libtest_harness else "bin",
+            root = _crate_root_src(ctx, "lib.rs" if use_libtest_harness else "main.rs"),
             srcs = ctx.files.srcs,
             deps = ctx.attr.deps,
             aliases = ctx.attr.aliases,
             output = test_binary,
             edition = _get_edition(ctx, toolchain),
-            rustc_env = ctx.attr.rustc_env,
+            rustc_env = rustc_env,
+            compile_data = _compile_data(ctx),
         )
 
-    return rustc_compile_action(
+    providers = rustc_compile_action(
         ctx = ctx,
         toolchain = toolchain,
         crate_info = target,
-        rust_flags = ["--test"],
+        rust_flags = ["--test"] if use_libtest_harness else [],
     )
+    return _merge_runfiles(providers, binaries_runfiles, test_binary)
+
+def _merge_runfiles(providers, runfiles, executable):
+    """Returns `providers`, with `runfiles` merged into the runfiles of their DefaultInfo.
+
+    A DefaultInfo created in Starlark doesn't expose its executable, so it's given as `executable`.
+    """
+    merged = []
+    for provider in providers:
+        if type(provider) == "DefaultInfo":
+            provider = DefaultInfo(
+                files = provider.files,
+                runfiles = provider.default_runfiles.merge(runfiles),
+                executable = executable,
+            )
+        merged.append(provider)
+    return merged
 
 def _rust_test_impl(ctx):
-    return _rust_test_common(ctx, ctx.outputs.executable)
+    # The test binary is placed in its own directory, as rustc names it after the crate, which
+    # would otherwise collide with the launcher script.
+    test_binary = ctx.actions.declare_file(
+        "{}.test/{}".format(ctx.label.name, determine_crate_name(ctx)),
+    )
+    providers = _rust_test_common(ctx, test_binary)
+    return _wrap_with_test_launcher(ctx, test_binary, providers)
 
-def _rust_benchmark_impl(ctx):
-    bench_script = ctx.outputs.executable
+def _rust_test_binary_impl(ctx):
+    crate_name = determine_crate_name(ctx)
+    if crate_name == ctx.label.name:
+        test_binary = ctx.outputs.executable
+    else:
+        # rustc names the binary after the crate.
+        test_binary = ctx.actions.declare_file("{}.test/{}".format(ctx.label.name, crate_name))
+    return _rust_test_common(ctx, test_binary)
+
+def _expand_env(ctx):
+    """Expands `$(rootpath)`, `$(execpath)` and `$(location)` in the `env` attribute."""
+    targets = ctx.attr.data + getattr(ctx.attr, "binaries", [])
+    return {key: ctx.expand_location(value, targets) for key, value in ctx.attr.env.items()}
 
-    # Build the underlying benchmark binary.
+def _wrap_with_launcher(ctx, binary, providers, command, runfiles):
+    """
+    Makes the target's executable a script which sets up the `env` attribute and runs `command`.
+
+    The script is run from the root of the target's runfiles by both `bazel run` and `bazel test`,
+    so `command` and the values of `env` refer to files by their runfiles paths.
+
+    Args:
+        ctx: The ctx object for the current target.
+        binary: The File object for the binary built by `rustc_compile_action`.
+        providers: The providers returned by `rustc_compile_action` for `binary`.
+        command: The command the script runs, followed by the script's arguments.
+        runfiles: The runfiles needed by `command`, besides those of `binary`.
+
+    Returns:
+        `providers`, with the DefaultInfo replaced by one for the script.
+    """
+    env = _expand_env(ctx)
+    ctx.actions.write(
+        output = ctx.outputs.executable,
+        content = "\n".join(
+            [
+                "#!/usr/bin/env bash",
+                "set -e",
+            ] +
+            ["export {}={}".format(key, shell.quote(env[key])) for key in sorted(env.keys())] +
+            ["exec {} \"$@\"".format(" ".join(command))],
+        ),
+        is_executable = True,
+    )
+
+    wrapped = []
+    default_info = None
+    for provider in providers:
+        if type(provider) == "DefaultInfo":
+            default_info = provider
+        else:
+            wrapped.append(provider)
+    if not default_info:
+        fail("No DefaultInfo provider returned from rustc_compile_action")
+
+    runfiles = ctx.runfiles(files = [binary]).merge(runfiles)
+    runfiles = runfiles.merge(default_info.default_runfiles)
+
+    return wrapped + [
+        DefaultInfo(
+            files = depset([ctx.outputs.executable, binary]),
+            runfiles = runfiles,
+            executable = ctx.outputs.executable,
+        ),
+    ]
+
+def _wrap_with_test_launcher(ctx, test_binary, providers, test_args = [], launcher_args = []):
+    """
+    Makes the target's executable run `test_binary` through the test launcher.
+
+    The launcher implements the parts of Bazel's test protocol that libtest doesn't know about,
+    such as sharding and `--test_filter`. See @io_bazel_rules_rust//tools/test_env:launcher.rs.
+
+    Args:
+        ctx: The ctx object for the current target.
+        test_binary: The File object for the test binary.
+        providers: The providers returned by `rustc_compile_action` for `test_binary`.
+        test_args: Arguments always passed to `test_binary`, ahead of those given by the user.
+        launcher_args: Options for the launcher itself.
+
+    Returns:
+        `providers`, with the DefaultInfo replaced by one for the launcher script.
+    """
+    launcher = ctx.executable._test_launcher
+    if not ctx.attr.use_libtest_harness:
+        launcher_args = ["--no_libtest_harness"] + launcher_args
+    runfiles = ctx.runfiles(files = [launcher])
+    runfiles = runfiles.merge(ctx.attr._test_launcher[DefaultInfo].default_runfiles)
+    return _wrap_with_launcher(
+        ctx,
+        test_binary,
+        providers,
+        command = [launcher.short_path] + launcher_args + [test_binary.short_path] + test_args,
+        runfiles = runfiles,
+    )
+
+def _rust_benchmark_impl(ctx):
+    # Build the underlying benchmark binary, in its own directory as rustc names it after the crate.
     bench_binary = ctx.actions.declare_file(
-        "{}_bin".format(bench_script.basename),
-        sibling = ctx.configuration.bin_dir,
+        "{}.bench/{}".format(ctx.label.name, determine_crate_name(ctx)),
     )
-    info = _rust_test_common(ctx, bench_binary)
+    providers = _rust_test_common(ctx, bench_binary)
+
+    output_dir = ctx.attr.output_dir or paths.join("bazel-benchmarks", ctx.label.package, ctx.label.name)
 
     # Wrap the benchmark to run it as cargo would.
-    ctx.actions.write(
-        output = bench_script,
-        content = "\n".join([
-            "#!/usr/bin/env bash",
-            "set -e",
-            "{} --bench".format(bench_binary.short_path),
-        ]),
-        is_executable = True,
+    return _wrap_with_test_launcher(
+        ctx,
+        bench_binary,
+        providers,
+        test_args = ["--bench"],
+        launcher_args = ["--output_dir=" + shell.quote(output_dir)],
     )
 
-    runfiles = ctx.runfiles(
-        files = info.runfiles + [bench_binary],
-        collect_data = True,
+_COMPILE_FAIL_TEST_SCRIPT = """#!/usr/bin/env bash
+set -euo pipefail
+
+update=0
+if [[ "${{1:-}}" == "--update" ]]; then
+  update=1
+  if [[ -z "${{BUILD_WORKSPACE_DIRECTORY:-}}" ]]; then
+    echo "--update must be run with 'bazel run {label} -- --update'" >&2
+    exit 1
+  fi
+fi
+
+failed=0
+
+# check <source> <exit code file> <actual stderr> <golden stderr, or '' if there is none>
+check() {{
+  local src="$1" exit_code="$(cat "$2")" actual="$3" golden="$4"
+
+  if [[ "$exit_code" == 0 ]]; then
+    echo "FAIL: $src compiled successfully, but was expected to fail." >&2
+    failed=1
+    return
+  elif [[ "$exit_code" != 1 ]]; then
+    echo "FAIL: rustc exited with $exit_code while compiling $src:" >&2
+    cat "$actual" >&2
+    failed=1
+    return
+  fi
+
+  if [[ "$update" == 1 ]]; then
+    if [[ "$src" == ../* ]]; then
+      echo "FAIL: Can't update the golden of $src, which is in an external repository." >&2
+      failed=1
+    else
+      cp "$actual" "$BUILD_WORKSPACE_DIRECTORY/${{src%.rs}}.stderr"
+      chmod u+w "$BUILD_WORKSPACE_DIRECTORY/${{src%.rs}}.stderr"
+      echo "Updated ${{src%.rs}}.stderr"
+    fi
+  elif [[ -z "$golden" ]]; then
+    echo "FAIL: $src has no ${{src%.rs}}.stderr golden to compare against. rustc's output was:" >&2
+    cat "$actual" >&2
+    failed=1
+  elif ! diff -u --label "$golden" --label "$src (actual)" "$golden" "$actual" >&2; then
+    echo "FAIL: The errors for $src don't match ${{src%.rs}}.stderr." >&2
+    failed=1
+  else
+    echo "PASS: $src"
+  fi
+}}
+
+{checks}
+
+if [[ "$failed" != 0 && "$update" == 0 ]]; then
+  echo >&2
+  echo "To update the goldens, run: bazel run {label} -- --update" >&2
+fi
+exit "$failed"
+"""
+
+def _rust_compile_fail_test_impl(ctx):
+    toolchain = find_toolchain(ctx)
+
+    goldens = {}
+    for golden in ctx.files.stderr:
+        goldens[golden.short_path] = golden
+
+    checks = []
+    runfiles = []
+    for src in ctx.files.srcs:
+        stem = src.basename[:-len(".rs")]
+        crate_name = stem.replace("-", "_")
+        prefix = "{}.compile_fail/{}".format(ctx.label.name, stem)
+        actual = ctx.actions.declare_file(prefix + ".stderr")
+        exit_code = ctx.actions.declare_file(prefix + ".exit_code")
+
+        # rustc only uses the output to find the directory it writes to. The binary is only
+        # written if the source unexpectedly compiles, so it isn't declared.
+        rustc = construct_rustc_invocation(
+            ctx = ctx,
+            toolchain = toolchain,
+            crate_info = CrateInfo(
+                name = crate_name,
+                type = "bin",
+                root = src,
+                srcs = [src],
+                deps = ctx.attr.deps,
+                aliases = ctx.attr.aliases,
+                output = actual,
+                edition = _get_edition(ctx, toolchain),
+                rustc_env = ctx.attr.rustc_env,
+                compile_data = _compile_data(ctx),
+            ),
+        )
+
+        # Normalize the diagnostics so that they don't depend on the toolchain's terminal colors,
+        # the location of the package, or trailing whitespace.
+        ctx.actions.run_shell(
+            command = " ".join([
+                rustc.command,
+                "2>&1 >/dev/null |",
+                "sed",
+                "-e $'s/\\x1b\\[[0-9;]*m//g'",
+                "-e 's|{}/||g'".format(REDACTED_PWD),
+                "-e 's|{}|$DIR/|g'".format(remap_path(ctx, src.dirname + "/")),
+                "-e 's/[[:space:]]*$//'",
+                "> {};".format(actual.path),
+                "echo \"${{PIPESTATUS[0]}}\" > {}".format(exit_code.path),
+            ]),
+            inputs = rustc.inputs,
+            outputs = [actual, exit_code],
+            env = rustc.env,
+            arguments = [rustc.args],
+            mnemonic = "RustcCompileFail",
+            progress_message = "Compiling Rust compile_fail test {} ({})".format(
+                ctx.label.name,
+                src.basename,
+            ),
+        )
+
+        golden = goldens.pop(src.short_path[:-len(".rs")] + ".stderr", None)
+        runfiles += [actual, exit_code] + ([golden] if golden else [])
+        checks.append("check {} {} {} {}".format(
+            src.short_path,
+            exit_code.short_path,
+            actual.short_path,
+            golden.short_path if golden else "''",
+        ))
+
+    if goldens:
+        fail("No source file found for {}".format(", ".join(sorted(goldens.keys()))), "stderr")
+
+    ctx.actions.write(
+        output = ctx.outputs.executable,
+        content = _COMPILE_FAIL_TEST_SCRIPT.format(
+            checks = "\n".join(checks),
+            label = ctx.label,
+        ),
+        is_executable = True,
     )
 
-    return struct(runfiles = runfiles)
+    return [
+        DefaultInfo(
+            runfiles = ctx.runfiles(files = runfiles),
+            executable = ctx.outputs.executable,
+        ),
+    ]
 
 def _tidy(doc_string):
     """Tidy excess whitespace in docstrings to not break index.md"""
@@ -264,9 +576,24 @@ _rust_common_attrs = {
             If `srcs` contains more than one file, then there must be a file either
             named `lib.rs`. Otherwise, `crate_root` must be set to the source file that
             is the root of the crate to be passed to rustc to build this crate.
+
+            The files rustc reads are checked against `srcs` and `compile_data`, and files
+            missing from them are reported as warnings. Set
+            `--@io_bazel_rules_rust//rust/settings:dep_info_validation` to `error` to fail the
+            build instead, or to `off` to skip the check. `srcs` which rustc didn't read are
+            always reported as warnings, as they may only be used by the crate's tests. The check needs Bazel 4.0 or later,
+            and is skipped by older versions.
         """),
         allow_files = [".rs"],
     ),
+    "crate_name": attr.string(
+        doc = _tidy("""
+            The name of the crate built by this rule, as it is referred to by `extern crate`
+            and `use` in the crates which depend on it.
+
+            Defaults to the name of the target, with any hyphens replaced by underscores.
+        """),
+    ),
     "crate_root": attr.label(
         doc = _tidy("""
             The file that will be passed to `rustc` to be used for building this crate.
@@ -276,14 +603,29 @@ _rust_common_attrs = {
         """),
         allow_single_file = [".rs"],
     ),
-    "data": attr.label_list(
+    "compile_data": attr.label_list(
         doc = _tidy("""
-            List of files used by this rule at runtime.
+            List of files used by this rule at compile time.
 
             This attribute can be used to specify any data files that are embedded into
             the library, such as via the
             [`include_str!`](https://doc.rust-lang.org/std/macro.include_str!.html)
-            macro.
+            macro. These files are inputs to rustc, but unlike `data`, they are not
+            added to the runfiles of the target.
+        """),
+        allow_files = True,
+    ),
+    "data": attr.label_list(
+        doc = _tidy("""
+            List of files used by this rule at runtime.
+
+            These files are added to the runfiles of the target, but are not inputs to
+            rustc, so changing them doesn't cause the crate to be rebuilt. Files which are
+            needed at compile time belong in `compile_data`.
+
+            Previously, `data` was used for both. While moving files over to
+            `compile_data`, the old behavior can be restored by building with
+            `--@io_bazel_rules_rust//rust/settings:data_is_compile_data`.
         """),
         allow_files = True,
     ),
@@ -305,6 +647,10 @@ _rust_common_attrs = {
     "rustc_env": attr.string_dict(
         doc = _tidy("""
             Dictionary of additional `"key": "value"` environment variables to set for rustc.
+
+            Values are subject to `$(execpath)` and `$(location)` expansion of the targets
+            in `compile_data`, giving paths relative to the directory rustc runs in, such as
+            those of files read by procedural macros.
         """),
     ),
     "crate_features": attr.string_list(
@@ -326,6 +672,24 @@ _rust_common_attrs = {
         doc = "A version to inject in the cargo environment variable.",
         default = "0.0.0",
     ),
+    "authors": attr.string_list(
+        doc = _tidy("""
+            The authors of the crate, exported to it at compile time as `CARGO_PKG_AUTHORS`,
+            separated by colons like Cargo does. These are read by eg. clap's `crate_authors!`.
+        """),
+    ),
+    "description": attr.string(
+        doc = "A description of the crate, exported to it at compile time as `CARGO_PKG_DESCRIPTION`.",
+    ),
+    "homepage": attr.string(
+        doc = "The URL of the crate's homepage, exported to it at compile time as `CARGO_PKG_HOMEPAGE`.",
+    ),
+    "repository": attr.string(
+        doc = "The URL of the crate's source repository, exported to it at compile time as `CARGO_PKG_REPOSITORY`.",
+    ),
+    "license": attr.string(
+        doc = "The SPDX license expression of the crate, exported to it at compile time as `CARGO_PKG_LICENSE`.",
+    ),
     "out_dir_tar": attr.label(
         doc = _tidy("""
             An optional tar or tar.gz file unpacked and passed as OUT_DIR.
@@ -340,6 +704,42 @@ _rust_common_attrs = {
         ],
     ),
     "_cc_toolchain": attr.label(default = "@bazel_tools//tools/cpp:current_cc_toolchain"),
+    "_data_is_compile_data": attr.label(
+        default = Label("@io_bazel_rules_rust//rust/settings:data_is_compile_data"),
+    ),
+    "_dep_info_validation": attr.label(
+        default = Label("@io_bazel_rules_rust//rust/settings:dep_info_validation"),
+    ),
+    "_fission": attr.label(
+        default = Label("@io_bazel_rules_rust//rust/settings:fission"),
+    ),
+    "_strip": attr.label(
+        default = Label("@io_bazel_rules_rust//rust/settings:strip"),
+    ),
+    "_whitelist_function_transition": attr.label(
+        default = "//tools/whitelists/function_transition_whitelist",
+    ),
+}
+
+_rust_channel_attrs = {
+    "channel": attr.string(
+        doc = _tidy("""
+            The release channel of the Rust toolchain to build this crate and its
+            dependencies with: "stable", "beta" or "nightly". By default, it is that of the
+            platform being built for, which is stable unless the platform has another
+            `@io_bazel_rules_rust//rust/settings:channel`.
+
+            Only binaries, tests and benchmarks have a channel. rustc can't load crates built
+            by another version of rustc (E0514), so the whole graph below them is switched
+            together; a library is built with the channel of whatever depends on it.
+
+            This switches to the variant of the platform for the channel, in
+            `@io_bazel_rules_rust//rust/platform/channels`, so it can only be set when building
+            for the host platform or one of `@io_bazel_rules_rust//rust/platform/triples`.
+            A toolchain for the channel must be registered, eg. with `rust_repository_set`.
+        """),
+        values = ["", "stable", "beta", "nightly"],
+    ),
 }
 
 _rust_library_attrs = {
@@ -352,9 +752,6 @@ _rust_library_attrs = {
         """),
         default = "rlib",
     ),
-    "_whitelist_function_transition": attr.label(
-        default = "//tools/whitelists/function_transition_whitelist",
-    ),
 }
 
 _rust_test_attrs = {
@@ -364,11 +761,48 @@ _rust_test_attrs = {
             Target inline tests declared in the given crate
 
             These tests are typically those that would be held out under
-            `#[cfg(test)]` declarations.
+            `#[cfg(test)]` declarations. The crate may be a `rust_library` or
+            a `rust_binary`.
+        """),
+        providers = [CrateInfo],
+    ),
+    "binaries": attr.label_list(
+        doc = _tidy("""
+            Binaries the test runs, like the binary targets of a Cargo package are for its integration tests.
+
+            Each binary is added to the test's runfiles, and its path is exported to the test
+            at compile time as `CARGO_BIN_EXE_<name>`, where `<name>` is the name of the
+            binary's target. Unlike the absolute path Cargo exports, the path is relative to
+            the directory the test is started in, the root of its runfiles, and is only valid
+            from there. It can be used as
+            `std::process::Command::new(env!("CARGO_BIN_EXE_<name>"))` as long as the test
+            doesn't change its working directory.
+        """),
+        cfg = "target",
+        executable = True,
+    ),
+    "use_libtest_harness": attr.bool(
+        default = True,
+        doc = _tidy("""
+            Whether to use libtest as the test harness, like `harness` in a Cargo target.
+
+            If False, the crate is built as a plain binary which provides its own `main`,
+            such as a test with a custom runner or a [criterion](https://crates.io/crates/criterion)
+            benchmark. It is still run through the test launcher, but is responsible
+            for handling sharding and `--test_filter` itself, which the
+            `@io_bazel_rules_rust//tools/test_env` crate helps with.
         """),
     ),
 }
 
+_rust_test_launcher_attrs = {
+    "_test_launcher": attr.label(
+        default = Label("@io_bazel_rules_rust//tools/test_env:launcher"),
+        executable = True,
+        cfg = "target",
+    ),
+}
+
 rust_library = rule(
     _rust_library_impl,
     attrs = dict(_rust_common_attrs.items() +
@@ -446,6 +880,33 @@ INFO: Elapsed time: 1.245s, Critical Path: 1.01s
 """,
 )
 
+_rust_env_attrs = {
+    "env": attr.string_dict(
+        doc = _tidy("""
+            Dictionary of additional `"key": "value"` environment variables to set when the
+            target is run with `bazel run` or `bazel test`.
+
+            Values are subject to `$(rootpath)`, `$(execpath)` and `$(location)` expansion
+            of the targets in `data`. The program is run from the root of its runfiles, so
+            `$(rootpath)` gives a path it can open. Unlike `rustc_env`, these variables are
+            not visible to `env!` at compile time.
+        """),
+    ),
+}
+
+_rust_benchmark_attrs = {
+    "output_dir": attr.string(
+        doc = _tidy("""
+            The directory, relative to the root of the workspace, which keeps the files the
+            benchmark writes to `TEST_UNDECLARED_OUTPUTS_DIR` when it's run with `bazel run`.
+
+            Criterion's reports, including the `estimates.json` of each benchmark, are written
+            to its `criterion` subdirectory. Defaults to `bazel-benchmarks/<package>/<name>`,
+            which is ignored with the other `bazel-*` directories by most `.gitignore` files.
+        """),
+    ),
+}
+
 _rust_binary_attrs = {
     "linker_script": attr.label(
         doc = _tidy("""
@@ -458,7 +919,11 @@ _rust_binary_attrs = {
 
 rust_binary = rule(
     _rust_binary_impl,
-    attrs = dict(_rust_common_attrs.items() + _rust_binary_attrs.items()),
+    attrs = dict(_rust_common_attrs.items() +
+                 _rust_binary_attrs.items() +
+                 _rust_channel_attrs.items() +
+                 _rust_env_attrs.items()),
+    cfg = channel_transition,
     executable = True,
     fragments = ["cpp"],
     host_fragments = ["cpp"],
@@ -549,13 +1014,38 @@ INFO: Elapsed time: 1.308s, Critical Path: 1.22s
 INFO: Running command line: bazel-bin/examples/rust/hello_world/hello_world
 Hello world
 ```
+
+Debug info:
+
+Like `cc_binary`, `rust_binary` follows Bazel's `--strip` and `--fission` flags.
+With `--strip=always`, or the default `--strip=sometimes` in `fastbuild` mode, the
+linker strips the debug info from the binary.
+
+When `--fission` applies to the compilation mode, the debug info is split from the
+binary instead, eg. to be uploaded to a symbol server. The output of the target is
+the stripped binary, and its debug info is written to a `.debug` file next to it,
+which is in the `debug_files` output group. Debug info is generated in this case
+even if the `debug_info` of the toolchain turns it off for the compilation mode.
+It is split with the `objcopy` of the C++ toolchain, as the default toolchain's
+rustc has no `-C split-debuginfo`, and only for ELF binaries.
+
+```
+$ bazel build -c opt --fission=opt --output_groups=+debug_files //hello_world
+Target //hello_world:hello_world up-to-date:
+  bazel-bin/hello_world/hello_world
+  bazel-bin/hello_world/hello_world.debug
+```
 """,
 )
 
 rust_test = rule(
     _rust_test_impl,
     attrs = dict(_rust_common_attrs.items() +
-                 _rust_test_attrs.items()),
+                 _rust_test_attrs.items() +
+                 _rust_test_launcher_attrs.items() +
+                 _rust_channel_attrs.items() +
+                 _rust_env_attrs.items()),
+    cfg = channel_transition,
     executable = True,
     fragments = ["cpp"],
     host_fragments = ["cpp"],
@@ -698,13 +1188,93 @@ rust_test(
 ```
 
 Run the test with `bazel build //hello_lib:hello_lib_test`.
+
+### Example: testing a binary
+
+The inline tests of a `rust_binary` are tested in the same way as those of a
+`rust_library`, by setting `crate` to the binary's target.
+
+Integration tests which run a binary list it in `binaries`, which makes its
+path available as `CARGO_BIN_EXE_<name>`, like Cargo does. Unlike Cargo's, the
+path is relative to the root of the test's runfiles, which is the working
+directory the test starts in, so it is only valid from there:
+
+`hello_world/tests/run.rs`:
+```rust
+use std::process::Command;
+
+#[test]
+fn test_run() {
+    let output = Command::new(env!("CARGO_BIN_EXE_hello_world")).output().unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "Hello world\\n");
+}
+```
+
+`hello_world/BUILD`:
+```python
+load("@io_bazel_rules_rust//rust:rust.bzl", "rust_binary", "rust_test")
+
+rust_binary(
+    name = "hello_world",
+    srcs = ["src/main.rs"],
+)
+
+rust_test(
+    name = "hello_world_test",
+    crate = ":hello_world",
+)
+
+rust_test(
+    name = "run_test",
+    srcs = ["tests/run.rs"],
+    binaries = [":hello_world"],
+)
+```
+
+### Example: sharding
+
+Tests are run through a launcher which implements Bazel's
+[test sharding protocol][sharding], so large suites can be split across several
+processes with the `shard_count` attribute. The test names are sorted and dealt
+out round-robin, so every shard runs a disjoint, stable subset of the tests.
+
+[sharding]: https://docs.bazel.build/versions/master/test-encyclopedia.html#test-sharding
+
+```python
+rust_test(
+    name = "hello_lib_test",
+    crate = ":hello_lib",
+    shard_count = 4,
+)
+```
+
+The launcher also writes a JUnit XML report of the individual test cases, with
+their timing, captured output and failure messages, to the `test.xml` file Bazel
+collects in `bazel-testlogs`. Nightly toolchains report precise timings through
+libtest's JSON events; on stable, timings are inferred from the console output.
+
+### Example: filtering tests
+
+`bazel test --test_filter=<filters>` runs only the selected test cases. The
+filter is a comma-separated list of patterns: a test is run if its name
+contains any of the patterns, or if it is equal to a pattern prefixed with `=`.
+
+```
+$ bazel test //hello_lib:hello_lib_test --test_filter=test::,=greeter::test_greeting
+```
+
+Tests which need the directories Bazel provides, such as `TEST_TMPDIR` or
+`TEST_UNDECLARED_OUTPUTS_DIR`, can depend on
+`@io_bazel_rules_rust//tools/test_env` for typed accessors.
 """,
 )
 
 rust_test_binary = rule(
-    _rust_test_impl,
+    _rust_test_binary_impl,
     attrs = dict(_rust_common_attrs.items() +
-                 _rust_test_attrs.items()),
+                 _rust_test_attrs.items() +
+                 _rust_channel_attrs.items()),
+    cfg = channel_transition,
     executable = True,
     fragments = ["cpp"],
     host_fragments = ["cpp"],
@@ -727,7 +1297,13 @@ See `rust_test` for example usage.
 
 rust_benchmark = rule(
     _rust_benchmark_impl,
-    attrs = _rust_common_attrs,
+    attrs = dict(_rust_common_attrs.items() +
+                 _rust_test_attrs.items() +
+                 _rust_test_launcher_attrs.items() +
+                 _rust_benchmark_attrs.items() +
+                 _rust_channel_attrs.items() +
+                 _rust_env_attrs.items()),
+    cfg = channel_transition,
     executable = True,
     fragments = ["cpp"],
     host_fragments = ["cpp"],
@@ -814,5 +1390,161 @@ rust_benchmark(
 ```
 
 Run the benchmark test using: `bazel run //fibonacci:fibonacci_bench`.
+
+The benchmarks to run can be selected by passing libtest filters as arguments
+(`bazel run //fibonacci:fibonacci_bench -- fibonacci`), or through the
+`TESTBRIDGE_TEST_ONLY` environment variable, using the same syntax as
+`bazel test --test_filter` for `rust_test`.
+
+### Example: criterion
+
+Benchmarks using a harness other than libtest, such as
+[criterion](https://crates.io/crates/criterion), work on stable Rust. They
+provide their own `main`, so they must be built with `use_libtest_harness = False`:
+
+```python
+rust_benchmark(
+    name = "fibonacci_criterion_bench",
+    srcs = ["benches/fibonacci_criterion_bench.rs"],
+    use_libtest_harness = False,
+    deps = [
+        ":fibonacci",
+        "//cargo:criterion",
+    ],
+)
+```
+
+criterion's reports, including the `estimates.json` of each benchmark, are
+written to the `criterion` directory of `TEST_UNDECLARED_OUTPUTS_DIR`. `bazel run`
+doesn't provide one, so it's the `output_dir` of the benchmark in the workspace,
+`bazel-benchmarks/fibonacci/fibonacci_criterion_bench` by default:
+
+```
+$ bazel run //fibonacci:fibonacci_criterion_bench
+$ cat bazel-benchmarks/fibonacci/fibonacci_criterion_bench/criterion/fibonacci/new/estimates.json
+```
+""",
+)
+
+rust_compile_fail_test = rule(
+    _rust_compile_fail_test_impl,
+    attrs = {
+        "srcs": attr.label_list(
+            doc = _tidy("""
+                List of Rust `.rs` source files which are expected to fail to compile.
+
+                Each source file is compiled on its own, as the root of a binary crate.
+            """),
+            allow_files = [".rs"],
+            mandatory = True,
+        ),
+        "stderr": attr.label_list(
+            doc = _tidy("""
+                The expected errors of each source file.
+
+                The errors of `foo.rs` are compared to `foo.stderr`, in the same directory.
+            """),
+            allow_files = [".stderr"],
+        ),
+        "deps": _rust_common_attrs["deps"],
+        "aliases": _rust_common_attrs["aliases"],
+        "compile_data": _rust_common_attrs["compile_data"],
+        "rustc_env": _rust_common_attrs["rustc_env"],
+        "crate_features": _rust_common_attrs["crate_features"],
+        "edition": _rust_common_attrs["edition"],
+        "rustc_flags": _rust_common_attrs["rustc_flags"],
+        "channel": _rust_channel_attrs["channel"],
+        "_cc_toolchain": _rust_common_attrs["_cc_toolchain"],
+        "_whitelist_function_transition": _rust_common_attrs["_whitelist_function_transition"],
+    },
+    cfg = channel_transition,
+    executable = True,
+    fragments = ["cpp"],
+    host_fragments = ["cpp"],
+    test = True,
+    toolchains = [
+        "@io_bazel_rules_rust//rust:toolchain",
+        "@bazel_tools//tools/cpp:toolchain_type",
+    ],
+    doc = """
+Tests that Rust sources fail to compile with the expected errors.
+
+Each source file is compiled with the same dependencies and flags as any other
+crate, and the test passes if every one of them fails to compile with errors
+matching its `.stderr` golden file. This is typically used to test the
+diagnostics of procedural macros, in the manner of
+[trybuild](https://crates.io/crates/trybuild).
+
+To make the goldens independent of where the sources are checked out, colors
+and trailing whitespace are removed from rustc's output, and the directory of
+the source file is replaced with `$DIR`.
+
+Example:
+
+Suppose you have the following directory structure for a procedural macro
+crate, `hello_macro`, which may only be derived for structs:
+
+```
+[workspace]/
+    WORKSPACE
+    hello_macro/
+        BUILD
+        src/
+            lib.rs
+        tests/
+            compile_fail/
+                derive_on_fn.rs
+                derive_on_fn.stderr
+```
+
+`hello_macro/tests/compile_fail/derive_on_fn.rs`:
+```rust
+extern crate hello_macro;
+
+#[derive(hello_macro::HelloWorld)]
+fn not_a_struct() {}
+
+fn main() {}
+```
+
+`hello_macro/tests/compile_fail/derive_on_fn.stderr`:
+```text
+error: `derive` may only be applied to structs, enums and unions
+ --> $DIR/derive_on_fn.rs:3:1
+  |
+3 | #[derive(hello_macro::HelloWorld)]
+  | ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+
+error: aborting due to previous error
+```
+
+To test that the macro is rejected:
+
+`hello_macro/BUILD`:
+```python
+load("@io_bazel_rules_rust//rust:rust.bzl", "rust_compile_fail_test", "rust_library")
+
+rust_library(
+    name = "hello_macro",
+    srcs = ["src/lib.rs"],
+    crate_type = "proc-macro",
+)
+
+rust_compile_fail_test(
+    name = "hello_macro_compile_fail_test",
+    srcs = glob(["tests/compile_fail/*.rs"]),
+    stderr = glob(["tests/compile_fail/*.stderr"]),
+    deps = [":hello_macro"],
+)
+```
+
+Run the test with `bazel test //hello_macro:hello_macro_compile_fail_test`.
+
+When a diagnostic changes, or a new source file is added, the goldens can be
+written by running the test with `--update`:
+
+```
+bazel run //hello_macro:hello_macro_compile_fail_test -- --update
+```
 """,
 )
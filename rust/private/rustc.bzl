@@ -12,6 +12,8 @@
 # See the License for the specific language governing permissions and
 # limitations under the License.
 
+load("@io_bazel_rules_rust//rust/platform:triple_mappings.bzl", "system_to_target_os", "triple_to_cfg")
+load("@io_bazel_rules_rust//rust:private/settings.bzl", "BuildSettingInfo")
 load("@io_bazel_rules_rust//rust:private/utils.bzl", "relative_path")
 load("@io_bazel_rules_rust//rust:private/legacy_cc_starlark_api_shim.bzl", "get_libs_for_static_executable")
 load(
@@ -36,6 +38,7 @@ CrateInfo = provider(
         "output": "File: The output File that will be produced, depends on crate type.",
         "edition": "str: The edition of this crate.",
         "rustc_env": """Dict[String, String]: Additional `"key": "value"` environment variables to set for rustc.""",
+        "compile_data": "depset[File]: Files needed only to compile this crate, eg. by `include_str!`.",
     },
 )
 
@@ -56,6 +59,17 @@ DepInfo = provider(
     },
 )
 
+# Turns the output of `rustc --print cfg` into shell commands exporting the configuration of the
+# target as Cargo does, eg. `target_feature="sse"` and `target_feature="sse2"` into
+# `CARGO_CFG_TARGET_FEATURE=sse,sse2` and `unix` into `CARGO_CFG_UNIX=`.
+_TARGET_CFG_TO_ENV_AWK = " ".join([
+    '/^(unix|windows)$/ { printf "export CARGO_CFG_%s=\\n", toupper($0) }',
+    '/^target_[a-z_]*=/ {',
+    'key = "CARGO_CFG_" toupper($1); gsub(/"/, "", $2);',
+    'if (key in cfg) { cfg[key] = cfg[key] "," $2 } else { cfg[key] = $2 } }',
+    'END { for (key in cfg) printf "export %s=%s\\n", key, cfg[key] }',
+])
+
 def _get_rustc_env(ctx, toolchain):
     version = ctx.attr.version if hasattr(ctx.attr, "version") else "0.0.0"
     major, minor, patch = version.split(".", 2)
@@ -63,26 +77,83 @@ def _get_rustc_env(ctx, toolchain):
         patch, pre = patch.split("-", 1)
     else:
         pre = ""
-    return {
+    env = {
         "CARGO_PKG_VERSION": version,
         "CARGO_PKG_VERSION_MAJOR": major,
         "CARGO_PKG_VERSION_MINOR": minor,
         "CARGO_PKG_VERSION_PATCH": patch,
         "CARGO_PKG_VERSION_PRE": pre,
-        "CARGO_PKG_AUTHORS": "",
+        "CARGO_PKG_AUTHORS": ":".join(getattr(ctx.attr, "authors", [])),
         "CARGO_PKG_NAME": ctx.label.name,
-        "CARGO_PKG_DESCRIPTION": "",
-        "CARGO_PKG_HOMEPAGE": "",
-        "CARGO_CFG_TARGET_OS": toolchain.os,
-        "CARGO_CFG_TARGET_ARCH": toolchain.target_arch,
+        "CARGO_PKG_DESCRIPTION": getattr(ctx.attr, "description", ""),
+        "CARGO_PKG_HOMEPAGE": getattr(ctx.attr, "homepage", ""),
+        "CARGO_PKG_REPOSITORY": getattr(ctx.attr, "repository", ""),
+        "CARGO_PKG_LICENSE": getattr(ctx.attr, "license", ""),
     }
 
-def _get_compilation_mode_opts(ctx, toolchain):
+    # The configuration of a custom target is only known to rustc, and exported when compiling.
+    if toolchain.target_json:
+        return env
+
+    # Like Cargo, export the configuration of the target, leaving out options rustc doesn't set.
+    for key, value in triple_to_cfg(toolchain.target_triple).items():
+        if value or key not in ("target_family", "target_feature"):
+            env["CARGO_CFG_" + key.upper()] = value
+    family = env.get("CARGO_CFG_TARGET_FAMILY")
+    if family:
+        env["CARGO_CFG_" + family.upper()] = ""
+    return env
+
+def _get_compilation_mode_opts(ctx, toolchain, crate_type, is_test, debug_info_mode):
+    """
+    Translates the toolchain's profile for the current compilation mode into rustc flags.
+
+    Args:
+      ctx: The ctx object for the current target.
+      toolchain: The Rust toolchain.
+      crate_type: str: The type of the crate being built.
+      is_test: bool: Whether the crate is built as a test.
+      debug_info_mode: str: What happens to the debug info, from `determine_debug_info_mode`.
+
+    Returns:
+      struct: with the following fields:
+              - flags: List[str]: The flags to pass to rustc.
+              - incremental: bool: Whether rustc compiles incrementally, outside of the sandbox.
+    """
     comp_mode = ctx.var["COMPILATION_MODE"]
     if not comp_mode in toolchain.compilation_mode_opts:
         fail("Unrecognized compilation mode {} for toolchain.".format(comp_mode))
+    profile = toolchain.compilation_mode_opts[comp_mode]
+
+    debug_info = profile.debug_info
+    if debug_info_mode == "split" and debug_info == "0":
+        # Splitting the debug info asks for it to be kept, rather than embedded in the binary.
+        debug_info = "2"
+
+    flags = [
+        "--codegen=opt-level=" + profile.opt_level,
+        "--codegen=debuginfo=" + debug_info,
+        "--codegen=debug-assertions=" + ("on" if profile.debug_assertions == "true" else "off"),
+        "--codegen=overflow-checks=" + ("on" if profile.overflow_checks == "true" else "off"),
+        "--codegen=codegen-units=" + profile.codegen_units,
+    ]
 
-    return toolchain.compilation_mode_opts[comp_mode]
+    # LTO and the panic strategy only apply when linking a final artifact, as with Cargo. Libraries
+    # carry the bitcode LTO needs anyway, and crates which unwind can be linked into binaries which
+    # abort, but not the other way around. Tests always unwind, as libtest catches their panics.
+    if crate_type in ("bin", "cdylib", "staticlib"):
+        if profile.lto != "false":
+            flags.append("--codegen=lto=" + ("fat" if profile.lto == "true" else profile.lto))
+        if not is_test:
+            flags.append("--codegen=panic=" + profile.panic)
+
+    if profile.incremental:
+        flags.append("--codegen=incremental=" + profile.incremental)
+
+    return struct(
+        flags = flags,
+        incremental = bool(profile.incremental),
+    )
 
 def get_lib_name(lib):
     """Returns the name of a library artifact, eg. libabc.a -> abc"""
@@ -150,7 +221,7 @@ def collect_deps(deps, aliases, toolchain):
         transitive_libs = transitive_libs.to_list(),
     )
 
-def _get_linker_and_args(ctx, rpaths):
+def _get_linker_and_args(ctx, rpaths, strip_debug_symbols = False):
     if (len(BAZEL_VERSION) == 0 or
         versions.is_at_least("0.18.0", BAZEL_VERSION)):
         user_link_flags = ctx.fragments.cpp.linkopts
@@ -176,6 +247,8 @@ def _get_linker_and_args(ctx, rpaths):
         is_linking_dynamic_library = False,
         runtime_library_search_directories = rpaths,
         user_link_flags = user_link_flags,
+        # This lets the cc toolchain tell the linker to strip debug symbols, as it does for `--strip`.
+        must_keep_debug = not strip_debug_symbols,
     )
     link_args = cc_common.get_memory_inefficient_command_line(
         feature_configuration = feature_configuration,
@@ -194,20 +267,206 @@ def _get_linker_and_args(ctx, rpaths):
 
     return ld, link_args, link_env
 
-def rustc_compile_action(
+# Compares the files rustc read, as listed in its dep-info, to the srcs and compile_data of a crate.
+#
+# Usage: <dep-info> <srcs file> <compile_data file> <OUT_DIR, or ""> <off|warn|error> <label> <output>
+_DEP_INFO_VALIDATION_SCRIPT = """
+set -euo pipefail
+dep_info="$1"
+srcs="$2"
+compile_data="$3"
+out_dir="$4"
+mode="$5"
+label="$6"
+output="$7"
+
+if [[ "$mode" == "error" ]]; then
+  severity="ERROR"
+else
+  severity="WARNING"
+fi
+
+# Each file rustc read is listed in the dep-info as an empty rule, "<path>:". Paths rustc made
+# absolute, such as those built from CARGO_MANIFEST_DIR, are matched to the declared files by
+# their suffix, as the execution root differs between actions.
+awk \\
+  -v severity="$severity" \\
+  -v label="$label" \\
+  -v out_dir="$out_dir" \\
+  '
+  FILENAME == ARGV[1] { srcs[++num_srcs] = $0; declared[$0] = 1; next }
+  FILENAME == ARGV[2] { declared[$0] = 1; next }
+  /^#/ || !/:$/ || /: / { next }
+  {
+    path = substr($0, 1, length($0) - 1)
+    gsub(/\\\\ /, " ", path)
+    if (!(path in declared) && substr(path, 1, 1) == "/") {
+      for (candidate in declared) {
+        suffix = "/" candidate
+        if (length(path) > length(suffix) &&
+            substr(path, length(path) - length(suffix) + 1) == suffix) {
+          path = candidate
+          break
+        }
+      }
+    }
+    if (path in declared) {
+      used[path] = 1
+      next
+    }
+    # Files generated into OUT_DIR are declared as a whole.
+    if (out_dir != "" && (index(path, out_dir "/") == 1 || index(path, "/" out_dir "/") > 0)) {
+      next
+    }
+    if (!(path in undeclared)) {
+      undeclared[path] = 1
+      print severity ": " label ": rustc read " path ", which is not in srcs or compile_data" > "/dev/stderr"
+      problems++
+    }
+  }
+  END {
+    # A source may only be compiled in another configuration, such as the #[cfg(test)] modules of
+    # a library, so unused sources are only ever warned about.
+    for (i = 1; i <= num_srcs; i++) {
+      if (!(srcs[i] in used)) {
+        print "WARNING: " label ": " srcs[i] " is in srcs, but is not used by the crate" > "/dev/stderr"
+      }
+    }
+    exit problems > 0 && severity == "ERROR"
+  }
+  ' "$srcs" "$compile_data" "$dep_info"
+
+touch "$output"
+"""
+
+# The prefixes which replace machine and configuration specific paths in rustc's outputs, such as
+# debuginfo, panic messages and `file!()`, to make them reproducible.
+REDACTED_PWD = "__bazel_redacted_pwd"
+REDACTED_BIN_DIR = "__bazel_redacted_bin"
+REDACTED_EXTERNAL = "__bazel_redacted_external"
+
+def _remapped_path_prefixes(ctx):
+    """
+    Returns the `(prefix, replacement)` pairs passed to rustc as `--remap-path-prefix`.
+
+    Paths may be relative to the execution root, or absolute if rustc was given them that way, eg.
+    through `CARGO_MANIFEST_DIR` or `OUT_DIR`, so both forms are remapped. The prefixes may refer
+    to `$(pwd)`, which is only known when the action runs. rustc applies the last matching prefix,
+    so the more specific ones come last. Each prefix ends with a `/`, so that sibling directories
+    such as `externals` aren't remapped.
+    """
+    return [
+        ("$(pwd)/", REDACTED_PWD + "/"),
+        ("$(pwd)/external/", REDACTED_EXTERNAL + "/"),
+        ("external/", REDACTED_EXTERNAL + "/"),
+        ("$(pwd)/" + ctx.bin_dir.path + "/", REDACTED_BIN_DIR + "/"),
+        (ctx.bin_dir.path + "/", REDACTED_BIN_DIR + "/"),
+    ]
+
+def remap_path(ctx, path):
+    """Returns the path rustc reports for `path`, which is relative to the execution root.
+
+    Like rustc, this replaces the last of the prefixes which `path` starts with.
+    """
+    for prefix, replacement in reversed(_remapped_path_prefixes(ctx)):
+        if not prefix.startswith("$(pwd)") and path.startswith(prefix):
+            return replacement + path[len(prefix):]
+    return path
+
+def determine_crate_name(ctx):
+    """
+    Returns the name of the crate built by the current target.
+
+    This is the `crate_name` attribute if it is set, and the name of the target otherwise, with
+    hyphens replaced by underscores.
+    """
+    crate_name = getattr(ctx.attr, "crate_name", "")
+    if not crate_name:
+        return ctx.label.name.replace("-", "_")
+    if not crate_name.replace("_", "").isalnum() or crate_name[0].isdigit():
+        fail(
+            ("{} is not a valid crate name. It may only contain letters, digits and " +
+             "underscores, and may not start with a digit.").format(repr(crate_name)),
+            "crate_name",
+        )
+    return crate_name
+
+def determine_output_hash(ctx):
+    """
+    Returns a hash which is unique to the crate built by the current target.
+
+    The hash is passed to rustc as `-C metadata` to disambiguate the symbols of crates with the
+    same name, and is part of the names of library outputs. It is derived from the label of the
+    target and its configuration, so it is the same in every build of the target, and differs
+    between targets which build crates from the same sources.
+    """
+    # `hash` returns a signed 32-bit int. Negating the smallest one overflows, so the sign bit is
+    # masked off instead.
+    return repr(hash(str(ctx.label) + ctx.bin_dir.path) & 0x7fffffff)
+
+def get_compile_data_targets(ctx):
+    """
+    Returns the targets whose files are needed to compile the current target.
+
+    These are the `compile_data` of the target. Its `data` is included as well while
+    `@io_bazel_rules_rust//rust/settings:data_is_compile_data` is set, which lets existing users
+    move the files they need at compile time over to `compile_data`.
+    """
+    targets = getattr(ctx.attr, "compile_data", [])
+    if (hasattr(ctx.attr, "_data_is_compile_data") and
+        ctx.attr._data_is_compile_data[BuildSettingInfo].value):
+        targets = targets + getattr(ctx.attr, "data", [])
+    return targets
+
+def determine_debug_info_mode(ctx, toolchain):
+    """
+    Returns what happens to the debug info of the binaries built by the current target.
+
+    This follows Bazel's flags for C++ binaries: the debug info is split into a separate file
+    when `--fission` applies to the compilation mode, and is stripped when `--strip` is "always",
+    or "sometimes" in `fastbuild` mode. Debug info is only split from ELF binaries, and only
+    binaries linked by the C++ toolchain are split or stripped.
+
+    Returns:
+      str: "split", "strip", or "embed" to leave the debug info in the binary.
+    """
+    if (not hasattr(ctx.attr, "_fission") or toolchain.target_arch == "wasm32" or
+        toolchain.rust_lld):
+        return "embed"
+    comp_mode = ctx.var["COMPILATION_MODE"]
+    fission = ctx.attr._fission[BuildSettingInfo].value
+    if ((fission == "yes" or comp_mode in fission.split(",")) and
+        toolchain.os not in ("darwin", "windows")):
+        return "split"
+    strip = ctx.attr._strip[BuildSettingInfo].value
+    if strip == "always" or (strip == "sometimes" and comp_mode == "fastbuild"):
+        return "strip"
+    return "embed"
+
+def construct_rustc_invocation(
         ctx,
         toolchain,
         crate_info,
         output_hash = None,
-        rust_flags = []):
+        rust_flags = [],
+        dep_info_file = None):
     """
-    Constructs the rustc command used to build the current target.
+    Constructs the rustc invocation which builds `crate_info` for the current target.
+
+    This is shared by every rule which runs rustc, so that they all see the same dependencies,
+    flags and environment. rustc writes its dep-info, the list of files it read, to
+    `dep_info_file` if one is given.
 
     Returns:
-      List[Provider]: A list of the following providers:
-                     - CrateInfo: info for the crate we just built; same as `crate_info` parameter.
-                     - DepInfo: The transitive dependencies of this crate.
-                     - DefaultInfo: The output file for this crate, and its runfiles.
+      struct: with the following fields:
+              - dep_info: DepInfo: The transitive dependencies of this crate.
+              - inputs: depset[File]: The inputs rustc reads.
+              - args: Args: The arguments to pass to `command`.
+              - env: Dict[String, String]: The environment to run `command` in.
+              - command: str: A shell command which runs rustc with the arguments passed as "$@".
+              - out_dir: File: The directory passed to rustc as OUT_DIR, or None.
+              - execution_requirements: Dict[String, String]: The execution requirements of the
+                action running `command`.
     """
     output_dir = crate_info.output.dirname
 
@@ -229,12 +488,14 @@ def rustc_compile_action(
 
     compile_inputs = depset(
         crate_info.srcs +
-        getattr(ctx.files, "data", []) +
         dep_info.transitive_libs +
         [toolchain.rustc] +
         toolchain.crosstool_files +
-        ([] if linker_script == None else [linker_script]),
+        ([] if linker_script == None else [linker_script]) +
+        ([] if toolchain.target_json == None else [toolchain.target_json]) +
+        ([] if toolchain.rust_lld == None else [toolchain.rust_lld]),
         transitive = [
+            crate_info.compile_data,
             toolchain.rustc_lib.files,
             toolchain.rust_lib.files,
             linker_depset,
@@ -254,18 +515,31 @@ def rustc_compile_action(
     args.add("--out-dir=" + output_dir)
     args.add("--codegen=extra-filename=" + extra_filename)
 
-    compilation_mode = _get_compilation_mode_opts(ctx, toolchain)
-    args.add("--codegen=opt-level=" + compilation_mode.opt_level)
-    args.add("--codegen=debuginfo=" + compilation_mode.debug_info)
+    debug_info_mode = determine_debug_info_mode(ctx, toolchain)
+    compilation_mode = _get_compilation_mode_opts(
+        ctx,
+        toolchain,
+        crate_info.type,
+        "--test" in rust_flags,
+        debug_info_mode,
+    )
+    args.add_all(compilation_mode.flags)
 
-    args.add("--emit=dep-info,link")
+    if dep_info_file:
+        args.add("--emit=dep-info={},link".format(dep_info_file.path))
+    else:
+        args.add("--emit=link")
     args.add("--color=always")
-    args.add("--target=" + toolchain.target_triple)
+    args.add("--target=" + toolchain.target_flag_value)
     if hasattr(ctx.attr, "crate_features"):
         args.add_all(getattr(ctx.attr, "crate_features"), before_each = "--cfg", format_each = 'feature="%s"')
     if hasattr(ctx.attr, "linker_script") and linker_script != None:
         args.add(linker_script.path, format = "--codegen=link-arg=-T%s")
 
+        # Scripts included by the linker script, such as the `memory.x` of embedded targets, are
+        # looked up next to it.
+        args.add("-Lnative=" + linker_script.dirname)
+
     # Gets the paths to the folders containing the standard library (or libcore)
     rust_lib_paths = depset([file.dirname for file in toolchain.rust_lib.files.to_list()]).to_list()
 
@@ -280,9 +554,17 @@ def rustc_compile_action(
 
     # Rust's built-in linker can handle linking wasm files. We don't want to attempt to use the cc
     # linker since it won't understand.
-    if toolchain.target_arch != "wasm32":
+    if toolchain.rust_lld:
+        # Bare-metal targets are linked by rust-lld, as there is no C toolchain for them.
+        args.add("--codegen=linker=" + toolchain.rust_lld.path)
+        args.add("--codegen=linker-flavor=ld.lld")
+    elif toolchain.target_arch != "wasm32":
         rpaths = _compute_rpaths(toolchain, output_dir, dep_info)
-        ld, link_args, link_env = _get_linker_and_args(ctx, rpaths)
+        ld, link_args, link_env = _get_linker_and_args(
+            ctx,
+            rpaths,
+            strip_debug_symbols = debug_info_mode == "strip",
+        )
         env.update(link_env)
         args.add("--codegen=linker=" + ld)
         args.add_joined("--codegen", link_args, join_with = " ", format_joined = "link-args=%s")
@@ -320,10 +602,89 @@ def rustc_compile_action(
     # and use `$(pwd)` which resolves the `exec_root` at action execution time.
     package_dir = ctx.build_file_path[:ctx.build_file_path.rfind("/")]
     manifest_dir_env = "CARGO_MANIFEST_DIR=$(pwd)/{} ".format(package_dir)
-    command = '{}{}{} "$@" --remap-path-prefix="$(pwd)"=__bazel_redacted_pwd'.format(
+    # The configuration of a custom target is read from its specification by rustc itself, and
+    # exported like that of built-in targets in `_get_rustc_env`. The architecture and OS the
+    # toolchain declares for it must match.
+    if toolchain.target_json:
+        target_os = system_to_target_os(toolchain.os)
+        target_cfg_env = (
+            'eval "$({rustc} --print cfg --target={json} | awk -F= \'{awk}\')" && ' +
+            'if [ "$CARGO_CFG_TARGET_ARCH" != "{arch}" ] || [ "$CARGO_CFG_TARGET_OS" != "{os}" ]; then ' +
+            'echo "The toolchain for {json} declares its target_arch and os as {arch} and {os}, ' +
+            'but the specification has $CARGO_CFG_TARGET_ARCH and $CARGO_CFG_TARGET_OS" >&2; exit 1; fi && '
+        ).format(
+            rustc = toolchain.rustc.path,
+            json = toolchain.target_json.path,
+            awk = _TARGET_CFG_TO_ENV_AWK,
+            arch = toolchain.target_arch,
+            os = target_os,
+        )
+    else:
+        target_cfg_env = ""
+    command = '{}{}{}{} "$@" {}'.format(
+        target_cfg_env,
         manifest_dir_env,
         out_dir_env,
         toolchain.rustc.path,
+        " ".join([
+            '--remap-path-prefix="{}"={}'.format(prefix, replacement)
+            for prefix, replacement in _remapped_path_prefixes(ctx)
+        ]),
+    )
+
+    # Update environment with user provided variables, which may refer to the paths of compile data.
+    compile_data = get_compile_data_targets(ctx)
+    env.update({
+        key: ctx.expand_location(value, compile_data)
+        for key, value in crate_info.rustc_env.items()
+    })
+
+    return struct(
+        dep_info = dep_info,
+        inputs = compile_inputs,
+        args = args,
+        env = env,
+        command = command,
+        out_dir = out_dir,
+        # rustc keeps its incremental state in a directory Bazel doesn't know about.
+        execution_requirements = {
+            "no-sandbox": "1",
+            "no-remote": "1",
+        } if compilation_mode.incremental else {},
+    )
+
+def rustc_compile_action(
+        ctx,
+        toolchain,
+        crate_info,
+        output_hash = None,
+        rust_flags = [],
+        stripped_output = None):
+    """
+    Constructs the rustc command used to build the current target.
+
+    If `stripped_output` is given, the debug info of the binary built by rustc is moved into a
+    `.debug` file next to it, and `stripped_output` is the binary without it. It is then the
+    output of the target, and the `.debug` file is in the `debug_files` output group.
+
+    Returns:
+      List[Provider]: A list of the following providers:
+                     - CrateInfo: info for the crate we just built; same as `crate_info` parameter.
+                     - DepInfo: The transitive dependencies of this crate.
+                     - DefaultInfo: The output file for this crate, and its runfiles.
+                     - OutputGroupInfo: The dep-info of this crate, and its validation.
+    """
+    dep_info_file = ctx.actions.declare_file(
+        crate_info.output.basename + ".d",
+        sibling = crate_info.output,
+    )
+    rustc = construct_rustc_invocation(
+        ctx,
+        toolchain,
+        crate_info,
+        output_hash = output_hash,
+        rust_flags = rust_flags,
+        dep_info_file = dep_info_file,
     )
 
     if hasattr(ctx.attr, "version") and ctx.attr.version != "0.0.0":
@@ -331,36 +692,136 @@ def rustc_compile_action(
     else:
         formatted_version = ""
 
-    # Update environment with user provided variables.
-    env.update(crate_info.rustc_env)
-
     ctx.actions.run_shell(
-        command = command,
-        inputs = compile_inputs,
-        outputs = [crate_info.output],
-        env = env,
-        arguments = [args],
+        command = rustc.command,
+        inputs = rustc.inputs,
+        outputs = [crate_info.output, dep_info_file],
+        env = rustc.env,
+        arguments = [rustc.args],
+        execution_requirements = rustc.execution_requirements,
         mnemonic = "Rustc",
         progress_message = "Compiling Rust {} {}{} ({} files)".format(
             crate_info.type, ctx.label.name, formatted_version, len(crate_info.srcs)
         ),
     )
     runfiles = ctx.runfiles(
-        files = dep_info.transitive_dylibs.to_list() + getattr(ctx.files, "data", []),
+        files = rustc.dep_info.transitive_dylibs.to_list() + getattr(ctx.files, "data", []),
         collect_data = True,
     )
 
+    output = crate_info.output
+    debug_files = []
+    if stripped_output:
+        debug_files.append(_split_debug_info(ctx, crate_info.output, stripped_output))
+        output = stripped_output
+
     return [
         crate_info,
-        dep_info,
+        rustc.dep_info,
         DefaultInfo(
             # nb. This field is required for cc_library to depend on our output.
-            files = depset([crate_info.output]),
+            files = depset([output]),
             runfiles = runfiles,
-            executable = crate_info.output if crate_info.type == "bin" else None,
+            executable = output if crate_info.type == "bin" else None,
+        ),
+        OutputGroupInfo(
+            dep_info = depset([dep_info_file]),
+            debug_files = depset(debug_files),
+            _validation = depset(_validate_dep_info(ctx, crate_info, dep_info_file, rustc.out_dir)),
         ),
     ]
 
+def _split_debug_info(ctx, binary, stripped_output):
+    """
+    Moves the debug info of `binary` into a separate file, like `--fission` does for C++.
+
+    The stripped binary records the name of the `.debug` file with a `.gnu_debuglink` section, so
+    that debuggers find it when it is next to the binary, or in a symbol server.
+
+    Returns:
+      File: The `.debug` file.
+    """
+    cc_toolchain = find_cpp_toolchain(ctx)
+    if not cc_toolchain.objcopy_executable:
+        fail("The C++ toolchain has no objcopy, which is needed to split debug info")
+
+    debug_file = ctx.actions.declare_file(
+        stripped_output.basename + ".debug",
+        sibling = stripped_output,
+    )
+    ctx.actions.run_shell(
+        command = '"$1" --only-keep-debug "$2" "$3" && "$1" --strip-debug --add-gnu-debuglink="$3" "$2" "$4"',
+        arguments = [
+            cc_toolchain.objcopy_executable,
+            binary.path,
+            debug_file.path,
+            stripped_output.path,
+        ],
+        inputs = depset([binary], transitive = [cc_toolchain.all_files]),
+        outputs = [debug_file, stripped_output],
+        mnemonic = "RustSplitDebugInfo",
+        progress_message = "Splitting debug info of Rust binary {}".format(ctx.label.name),
+    )
+    return debug_file
+
+def _validate_dep_info(ctx, crate_info, dep_info_file, out_dir):
+    """
+    Checks that the files rustc read are exactly those declared in srcs and compile_data.
+
+    Undeclared files can only be read when actions aren't sandboxed, and would be missing
+    elsewhere. Files in `out_dir` are only declared as a whole, so are always accepted. Unused
+    srcs needlessly cause the crate to be rebuilt when they change, but may only be used in
+    another configuration, such as the `#[cfg(test)]` modules of a library which are only
+    compiled into its tests, so they are reported as warnings even in `error` mode.
+
+    The validation action is run through the `_validation` output group, which Bazel 4.0 and
+    later build by default. Older versions of Bazel ignore it, so the check is skipped.
+
+    Returns:
+      List[File]: The output of the validation action, if validation is enabled.
+    """
+    if not hasattr(ctx.attr, "_dep_info_validation"):
+        return []
+    mode = ctx.attr._dep_info_validation[BuildSettingInfo].value
+    if mode == "off":
+        return []
+
+    srcs = ctx.actions.declare_file(crate_info.output.basename + ".srcs", sibling = crate_info.output)
+    ctx.actions.write(srcs, "".join([src.path + "\n" for src in crate_info.srcs]))
+    compile_data = ctx.actions.declare_file(
+        crate_info.output.basename + ".compile_data",
+        sibling = crate_info.output,
+    )
+    ctx.actions.write(
+        compile_data,
+        "".join([f.path + "\n" for f in crate_info.compile_data.to_list()]),
+    )
+
+    output = ctx.actions.declare_file(
+        crate_info.output.basename + ".dep_info_validation",
+        sibling = crate_info.output,
+    )
+    ctx.actions.run_shell(
+        command = _DEP_INFO_VALIDATION_SCRIPT,
+        arguments = [
+            dep_info_file.path,
+            srcs.path,
+            compile_data.path,
+            out_dir.path if out_dir else "",
+            mode,
+            str(ctx.label),
+            output.path,
+        ],
+        inputs = [dep_info_file, srcs, compile_data],
+        outputs = [output],
+        mnemonic = "RustDepInfoValidation",
+        progress_message = "Validating the dep-info of Rust {} {}".format(
+            crate_info.type,
+            ctx.label.name,
+        ),
+    )
+    return [output]
+
 def add_edition_flags(args, crate):
     if crate.edition != "2015":
         args.add("--edition={}".format(crate.edition))
@@ -84,10 +84,26 @@ def _build_rustdoc_test_script(toolchain, dep_info, crate):
 
 set -e;
 
+# Translate Bazel's --test_filter into libtest filters, using the same syntax as rust_test.
+# Unlike rust_test, libtest applies `--exact` to every filter, so exact and substring filters
+# shouldn't be mixed.
+test_args=()
+if [[ -n "${{TESTBRIDGE_TEST_ONLY:-}}" ]]; then
+    IFS=',' read -r -a filters <<< "$TESTBRIDGE_TEST_ONLY"
+    for filter in "${{filters[@]}}"; do
+        if [[ "$filter" == =* ]]; then
+            test_args+=("--test-args=--exact" "--test-args=${{filter#=}}")
+        elif [[ -n "$filter" ]]; then
+            test_args+=("--test-args=$filter")
+        fi
+    done
+fi
+
 {rust_doc} --test \\
     {crate_root} \\
     --crate-name={crate_name} \\
-    {flags}
+    {flags} \\
+    "${{test_args[@]}}"
 """.format(
         rust_doc = toolchain.rust_doc.path,
         crate_root = crate.root.path,
@@ -0,0 +1,71 @@
+# Copyright 2020 The Bazel Authors. All rights reserved.
+#
+# Licensed under the Apache License, Version 2.0 (the "License");
+# you may not use this file except in compliance with the License.
+# You may obtain a copy of the License at
+#
+#    http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+
+"""
+Build settings which configure the rules, set on the command line with eg.
+`--@io_bazel_rules_rust//rust/settings:<name>=<value>`.
+"""
+
+BuildSettingInfo = provider(
+    doc = "The value of a build setting.",
+    fields = {
+        "value": "The value of the build setting in the current configuration.",
+    },
+)
+
+def _build_setting_impl(ctx):
+    return BuildSettingInfo(value = ctx.build_setting_value)
+
+bool_flag = rule(
+    implementation = _build_setting_impl,
+    build_setting = config.bool(flag = True),
+    doc = "A bool-typed build setting that can be set on the command line.",
+)
+
+def _string_flag_impl(ctx):
+    value = ctx.build_setting_value
+    if ctx.attr.values and value not in ctx.attr.values:
+        fail("Invalid value {} for {}. Expected one of: {}".format(
+            repr(value),
+            ctx.label,
+            ", ".join(ctx.attr.values),
+        ))
+    return BuildSettingInfo(value = value)
+
+string_flag = rule(
+    implementation = _string_flag_impl,
+    build_setting = config.string(flag = True),
+    attrs = {
+        "values": attr.string_list(
+            doc = "The allowed values of the setting. Any value is allowed if this is empty.",
+        ),
+    },
+    doc = "A string-typed build setting that can be set on the command line.",
+)
+
+def _native_flag_impl(ctx):
+    return BuildSettingInfo(value = ctx.attr.value)
+
+native_flag = rule(
+    implementation = _native_flag_impl,
+    attrs = {
+        "value": attr.string(
+            doc = "The value of the flag, as a `select` on `config_setting`s matching its values.",
+        ),
+    },
+    doc = """
+Exposes the value of one of Bazel's own flags, which rules can't read directly, as a
+`BuildSettingInfo`.
+""",
+)
@@ -1,20 +1,100 @@
+load("@io_bazel_rules_rust//rust/platform:platform.bzl", "RUST_CHANNELS", "SUPPORTED_PLATFORM_TRIPLES")
+
+# The platforms Bazel uses for the host, by default, in each of the versions it supports.
+_HOST_PLATFORMS = [
+    Label("@local_config_platform//:host"),
+    Label("@bazel_tools//platforms:host_platform"),
+]
+
+# The platforms in @io_bazel_rules_rust//rust/platform/triples, and their triples.
+_TRIPLE_PLATFORMS = {
+    Label("//rust/platform/triples:" + triple): triple
+    for triple in SUPPORTED_PLATFORM_TRIPLES
+}
+
+# The platforms in @io_bazel_rules_rust//rust/platform/channels, and the host or triple and the
+# channel of each.
+_CHANNEL_PLATFORMS = {
+    Label("//rust/platform/channels:{}-{}".format(name, channel)): (name, channel)
+    for name in ["host"] + SUPPORTED_PLATFORM_TRIPLES
+    for channel in RUST_CHANNELS
+}
+
+def _current_platform(settings):
+    platforms = settings["//command_line_option:platforms"]
+    return platforms[0] if platforms else _HOST_PLATFORMS[0]
+
+def _platform_channel(platform):
+    """Returns the Rust release channel of a platform, or None if it has the default one."""
+    if platform not in _CHANNEL_PLATFORMS:
+        return None
+    return _CHANNEL_PLATFORMS[platform][1]
+
+def _channel_platform(platform, channel):
+    """Returns the variant of `platform` whose Rust toolchains are from `channel`.
+
+    Only the host platform and those in @io_bazel_rules_rust//rust/platform/triples have variants
+    for each channel, in @io_bazel_rules_rust//rust/platform/channels.
+    """
+    if platform in _HOST_PLATFORMS:
+        name = "host"
+    elif platform in _TRIPLE_PLATFORMS:
+        name = _TRIPLE_PLATFORMS[platform]
+    elif platform in _CHANNEL_PLATFORMS:
+        name = _CHANNEL_PLATFORMS[platform][0]
+    else:
+        fail("The Rust channel can only be set for the host platform or those in " +
+             "@io_bazel_rules_rust//rust/platform/triples, not " + str(platform))
+    return str(Label("//rust/platform/channels:{}-{}".format(name, channel)))
+
+def _channel_transition(settings, attr):
+    channel = getattr(attr, "channel", "")
+    if not channel:
+        return settings
+    return {"//command_line_option:platforms": _channel_platform(_current_platform(settings), channel)}
+
 def _proc_macro_host_transition(settings, attr):
     if hasattr(attr, "crate_type") and attr.crate_type == "proc-macro":
-        return {"//command_line_option:platforms": "@local_config_platform//:host"}
+        # rustc loads proc-macros, so they are built by the same channel as the crates using them.
+        channel = _platform_channel(_current_platform(settings))
+        if channel:
+            return {"//command_line_option:platforms": _channel_platform(_HOST_PLATFORMS[0], channel)}
+        return {"//command_line_option:platforms": str(_HOST_PLATFORMS[0])}
     else:
         return settings
 
 def _wasm_bindgen_transition(settings, attr):
     return {"//command_line_option:platforms": "@io_bazel_rules_rust//rust/platform:wasm"}
 
+def _platform_transition(settings, attr):
+    if attr.platform:
+        return {"//command_line_option:platforms": str(attr.platform)}
+    else:
+        return settings
+
 proc_macro_host_transition = transition(
     implementation = _proc_macro_host_transition,
     inputs = ["//command_line_option:platforms"],
     outputs = ["//command_line_option:platforms"],
 )
 
+# Builds a target, and its dependencies, with the Rust toolchains of the channel in its `channel`
+# attribute, if it is set.
+channel_transition = transition(
+    implementation = _channel_transition,
+    inputs = ["//command_line_option:platforms"],
+    outputs = ["//command_line_option:platforms"],
+)
+
 wasm_bindgen_transition = transition(
     implementation = _wasm_bindgen_transition,
     inputs = [],
     outputs = ["//command_line_option:platforms"],
 )
+
+# Builds a target, and its toolchains, for the platform in its `platform` attribute, if it has one.
+platform_transition = transition(
+    implementation = _platform_transition,
+    inputs = ["//command_line_option:platforms"],
+    outputs = ["//command_line_option:platforms"],
+)
@@ -1,13 +1,30 @@
 load(":known_shas.bzl", "FILE_KEY_TO_SHA")
-load("//rust/platform:triple_mappings.bzl", "system_to_binary_ext", "system_to_dylib_ext", "system_to_staticlib_ext", "triple_to_constraint_set", "triple_to_system")
+load("//rust/platform:platform.bzl", "SUPPORTED_PLATFORM_TRIPLES")
+load("//rust/platform:triple_mappings.bzl", "system_to_binary_ext", "system_to_dylib_ext", "system_to_staticlib_ext", "triple_to_constraint_set", "triple_to_exec_constraint_set", "triple_to_system", "triple_to_toolchain_target_constraint_set")
 load("@bazel_tools//tools/build_defs/repo:utils.bzl", "maybe")
 load("@bazel_tools//tools/build_defs/repo:http.bzl", "http_archive")
 
 DEFAULT_TOOLCHAIN_NAME_PREFIX = "toolchain_for"
 
-def rust_repositories(version = "1.39.0", iso_date = None, rustfmt_version = "1.4.8"):
+# The templates of the URLs Rust archives are downloaded from, in which `{}` is replaced by the
+# path of an archive below `dist`, eg. `rustc-1.39.0-x86_64-unknown-linux-gnu`.
+DEFAULT_STATIC_RUST_URL_TEMPLATES = ["https://static.rust-lang.org/dist/{}.tar.gz"]
+
+def rust_repositories(
+        version = "1.39.0",
+        iso_date = None,
+        rustfmt_version = "1.4.8",
+        urls = DEFAULT_STATIC_RUST_URL_TEMPLATES,
+        sha256s = None,
+        strict_checksums = False,
+        rust_toolchain_file = None,
+        components = []):
     """Emits a default set of toolchains for Linux, OSX, and Freebsd
 
+    The cfg options of their targets are available for `select` as `@rust_cfg//:<option>`, eg.
+    `@rust_cfg//:target_os=linux`, on the hosts of these toolchains. See
+    `rust_target_cfg_repository`.
+
     Skip this macro and call the `rust_repository_set` macros directly if you need a compiler for
     other hosts or for additional target triples.
 
@@ -15,8 +32,23 @@ def rust_repositories(version = "1.39.0", iso_date = None, rustfmt_version = "1.
       version: The version of Rust. Either "nightly", "beta", or an exact version.
       rustfmt_version: The version of rustfmt. Either "nightly", "beta", or an exact version.
       iso_date: The date of the nightly or beta release (or None, if the version is a specific version).
+      urls: The templates of the URLs to download the archives from, tried in order. See
+            `rust_toolchain_repository`.
+      sha256s: Checksums of the archives, in addition to the known ones.
+      strict_checksums: Whether to fail rather than download an archive whose checksum is unknown.
+      rust_toolchain_file: A `rust-toolchain` or `rust-toolchain.toml` file, as used by rustup and
+                           Cargo, whose `channel`, `components` and `targets` give the toolchains.
+                           `version`, `iso_date` and `rustfmt_version` are ignored if it is set.
+      components: Other components to download, among "cargo", "clippy", "llvm-tools-preview" and
+                  "rust-src". See `rust_toolchain_repository`.
     """
 
+    # The repositories read the versions from the file.
+    if rust_toolchain_file:
+        version = None
+        iso_date = None
+        rustfmt_version = None
+
     maybe(
         http_archive,
         name = "rules_cc",
@@ -33,6 +65,11 @@ def rust_repositories(version = "1.39.0", iso_date = None, rustfmt_version = "1.
         version = version,
         iso_date = iso_date,
         rustfmt_version = rustfmt_version,
+        urls = urls,
+        sha256s = sha256s,
+        strict_checksums = strict_checksums,
+        rust_toolchain_file = rust_toolchain_file,
+        components = components,
     )
 
     rust_repository_set(
@@ -42,6 +79,11 @@ def rust_repositories(version = "1.39.0", iso_date = None, rustfmt_version = "1.
         version = version,
         iso_date = iso_date,
         rustfmt_version = rustfmt_version,
+        urls = urls,
+        sha256s = sha256s,
+        strict_checksums = strict_checksums,
+        rust_toolchain_file = rust_toolchain_file,
+        components = components,
     )
 
     rust_repository_set(
@@ -51,6 +93,26 @@ def rust_repositories(version = "1.39.0", iso_date = None, rustfmt_version = "1.
         version = version,
         iso_date = iso_date,
         rustfmt_version = rustfmt_version,
+        urls = urls,
+        sha256s = sha256s,
+        strict_checksums = strict_checksums,
+        rust_toolchain_file = rust_toolchain_file,
+        components = components,
+    )
+
+    rust_target_cfg_repository(
+        name = "rust_cfg",
+        rustcs = {
+            "@rust_linux_x86_64//:bin/rustc": "x86_64-unknown-linux-gnu",
+            "@rust_darwin_x86_64//:bin/rustc": "x86_64-apple-darwin",
+            "@rust_freebsd_x86_64//:bin/rustc": "x86_64-unknown-freebsd",
+        },
+        target_triples = [
+            "x86_64-unknown-linux-gnu",
+            "x86_64-apple-darwin",
+            "x86_64-unknown-freebsd",
+            "wasm32-unknown-unknown",
+        ],
     )
 
 def _check_version_valid(version, iso_date, param_prefix = ""):
@@ -94,11 +156,35 @@ filegroup(
     visibility = ["//visibility:public"],
 )
 
+filegroup(
+    name = "rust_debugger_scripts",
+    srcs = glob(["lib/rustlib/etc/*"]),
+    visibility = ["//visibility:public"],
+)
+
+filegroup(
+    name = "rust_gdb",
+    srcs = glob(["bin/rust-gdb"]),
+    visibility = ["//visibility:public"],
+)
+
+filegroup(
+    name = "rust_lldb",
+    srcs = glob(["bin/rust-lldb"]),
+    visibility = ["//visibility:public"],
+)
+
 filegroup(
     name = "rustdoc",
     srcs = ["bin/rustdoc{binary_ext}"],
     visibility = ["//visibility:public"],
 )
+
+filegroup(
+    name = "rust_lld",
+    srcs = glob(["lib/rustlib/{target_triple}/bin/rust-lld{binary_ext}"]),
+    visibility = ["//visibility:public"],
+)
 """.format(
         binary_ext = system_to_binary_ext(system),
         staticlib_ext = system_to_staticlib_ext(system),
@@ -131,6 +217,91 @@ sh_binary(
         target_triple = target_triple,
     )
 
+def BUILD_for_llvm_tools(target_triple):
+    """Emits a BUILD file the llvm-tools .tar.gz."""
+
+    system = triple_to_system(target_triple)
+    return """
+filegroup(
+    name = "llvm_objcopy",
+    srcs = ["lib/rustlib/{target_triple}/bin/llvm-objcopy{binary_ext}"],
+    visibility = ["//visibility:public"],
+)
+
+filegroup(
+    name = "llvm_size",
+    srcs = ["lib/rustlib/{target_triple}/bin/llvm-size{binary_ext}"],
+    visibility = ["//visibility:public"],
+)
+
+# llvm-profdata and llvm-cov, which coverage needs, are missing from older versions of the component.
+filegroup(
+    name = "llvm_profdata",
+    srcs = glob(["lib/rustlib/{target_triple}/bin/llvm-profdata{binary_ext}"]),
+    visibility = ["//visibility:public"],
+)
+
+filegroup(
+    name = "llvm_cov",
+    srcs = glob(["lib/rustlib/{target_triple}/bin/llvm-cov{binary_ext}"]),
+    visibility = ["//visibility:public"],
+)
+
+filegroup(
+    name = "llvm_tools",
+    srcs = glob(["lib/rustlib/{target_triple}/bin/*"]),
+    visibility = ["//visibility:public"],
+)
+""".format(
+        binary_ext = system_to_binary_ext(system),
+        target_triple = target_triple,
+    )
+
+def BUILD_for_cargo(target_triple):
+    """Emits a BUILD file the cargo .tar.gz."""
+
+    system = triple_to_system(target_triple)
+    return """
+filegroup(
+    name = "cargo",
+    srcs = ["bin/cargo{binary_ext}"],
+    visibility = ["//visibility:public"],
+)
+""".format(
+        binary_ext = system_to_binary_ext(system),
+    )
+
+def BUILD_for_clippy(target_triple):
+    """Emits a BUILD file the clippy .tar.gz."""
+
+    system = triple_to_system(target_triple)
+    return """
+filegroup(
+    name = "clippy_driver",
+    srcs = ["bin/clippy-driver{binary_ext}"],
+    visibility = ["//visibility:public"],
+)
+
+filegroup(
+    name = "cargo_clippy",
+    srcs = ["bin/cargo-clippy{binary_ext}"],
+    visibility = ["//visibility:public"],
+)
+""".format(
+        binary_ext = system_to_binary_ext(system),
+    )
+
+def BUILD_for_rust_src():
+    """Emits a BUILD file the rust-src .tar.gz."""
+
+    return """
+filegroup(
+    name = "rust_src",
+    srcs = glob(["lib/rustlib/src/rust/**"]),
+    visibility = ["//visibility:public"],
+)
+"""
+
 def BUILD_for_stdlib(target_triple):
     """Emits a BUILD file the stdlib .tar.gz."""
 
@@ -156,7 +327,15 @@ filegroup(
         target_triple = target_triple,
     )
 
-def BUILD_for_rust_toolchain(workspace_name, name, exec_triple, target_triple, default_edition = "2015"):
+def BUILD_for_rust_toolchain(
+        workspace_name,
+        name,
+        exec_triple,
+        target_triple,
+        default_edition = "2015",
+        include_llvm_tools = False,
+        include_rustfmt = True,
+        components = []):
     """Emits a toolchain declaration to match an existing compiler and stdlib.
 
     Args:
@@ -164,18 +343,68 @@ def BUILD_for_rust_toolchain(workspace_name, name, exec_triple, target_triple, d
       name: The name of the toolchain declaration
       exec_triple: The rust-style target that this compiler runs on
       target_triple: The rust-style target triple of the tool
+      include_llvm_tools: Whether the workspace contains the llvm-tools of `exec_triple`
+      include_rustfmt: Whether the workspace contains rustfmt
+      components: The other components the workspace contains, among "cargo", "clippy" and
+                  "rust-src"
     """
 
     system = triple_to_system(target_triple)
 
+    # Bare-metal targets only have `core` and `alloc`, which are built to abort on panics, and
+    # there is no C toolchain to link them, so rust-lld links them instead.
+    if system == "none":
+        bare_metal_attrs = """
+    rust_lld = "@{workspace_name}//:rust_lld",
+    panic = {{
+        "opt": "abort",
+        "dbg": "abort",
+        "fastbuild": "abort",
+    }},""".format(workspace_name = workspace_name)
+    else:
+        bare_metal_attrs = ""
+
+    if include_llvm_tools:
+        llvm_tools_attrs = """
+    llvm_objcopy = "@{workspace_name}//:llvm_objcopy",
+    llvm_size = "@{workspace_name}//:llvm_size",
+    llvm_tools = "@{workspace_name}//:llvm_tools",""".format(workspace_name = workspace_name)
+    else:
+        llvm_tools_attrs = ""
+
+    if include_rustfmt:
+        rustfmt_attr = """
+    rustfmt = "@{workspace_name}//:rustfmt_bin",""".format(workspace_name = workspace_name)
+    else:
+        rustfmt_attr = ""
+
+    component_attrs = ""
+    if "cargo" in components:
+        component_attrs += """
+    cargo = "@{workspace_name}//:cargo",""".format(workspace_name = workspace_name)
+    if "clippy" in components:
+        component_attrs += """
+    clippy_driver = "@{workspace_name}//:clippy_driver",""".format(workspace_name = workspace_name)
+    if "rust-src" in components:
+        component_attrs += """
+    rust_src = "@{workspace_name}//:rust_src",""".format(workspace_name = workspace_name)
+
+    # The debugger wrappers are shell scripts, which aren't shipped for Windows.
+    if triple_to_system(exec_triple) == "windows":
+        debugger_attrs = ""
+    else:
+        debugger_attrs = """
+    rust_gdb = "@{workspace_name}//:rust_gdb",
+    rust_lldb = "@{workspace_name}//:rust_lldb",
+    rust_debugger_scripts = "@{workspace_name}//:rust_debugger_scripts",""".format(workspace_name = workspace_name)
+
     return """
 rust_toolchain(
     name = "{toolchain_name}_impl",
     rust_doc = "@{workspace_name}//:rustdoc",
     rust_lib = "@{workspace_name}//:rust_lib-{target_triple}",
-    rustc = "@{workspace_name}//:rustc",
-    rustfmt = "@{workspace_name}//:rustfmt_bin",
-    rustc_lib = "@{workspace_name}//:rustc_lib",
+    rustc = "@{workspace_name}//:rustc",{rustfmt_attr}
+    rustc_lib = "@{workspace_name}//:rustc_lib",{debugger_attrs}{bare_metal_attrs}{llvm_tools_attrs}{component_attrs}
     staticlib_ext = "{staticlib_ext}",
     dylib_ext = "{dylib_ext}",
     os = "{system}",
@@ -187,6 +416,11 @@ rust_toolchain(
 """.format(
         toolchain_name = name,
         workspace_name = workspace_name,
+        rustfmt_attr = rustfmt_attr,
+        debugger_attrs = debugger_attrs,
+        bare_metal_attrs = bare_metal_attrs,
+        llvm_tools_attrs = llvm_tools_attrs,
+        component_attrs = component_attrs,
         staticlib_ext = system_to_staticlib_ext(system),
         dylib_ext = system_to_dylib_ext(system),
         system = system,
@@ -195,7 +429,44 @@ rust_toolchain(
         target_triple = target_triple,
     )
 
-def BUILD_for_toolchain(name, parent_workspace_name, exec_triple, target_triple):
+def BUILD_for_bare_metal_cc_toolchain(target_triples):
+    """Emits a toolchain for the dummy C++ toolchain of bare-metal targets, if any are in `target_triples`.
+
+    Bare-metal targets are linked by rust-lld, so they don't need a cc toolchain, but rules which
+    depend on one resolve it anyway. This is only declared where a bare-metal target is, so that it
+    doesn't take precedence over a real cc toolchain registered for those platforms otherwise.
+    """
+    if not [triple for triple in target_triples if triple_to_system(triple) == "none"]:
+        return ""
+    return """
+toolchain(
+    name = "dummy_cc_bare_metal_toolchain",
+    target_compatible_with = ["@io_bazel_rules_rust//rust/platform/os:none"],
+    toolchain = "@io_bazel_rules_rust//rust/private/dummy_cc_toolchain:dummy_cc_bare_metal",
+    toolchain_type = "@bazel_tools//tools/cpp:toolchain_type",
+)
+"""
+
+def version_to_channel(version):
+    """Returns the release channel of a Rust version, eg. "nightly" for "nightly" or "1.41.0-nightly"."""
+    for channel in ("beta", "nightly"):
+        if version == channel or ("-" + channel) in version:
+            return channel
+    return "stable"
+
+def BUILD_for_toolchain(name, parent_workspace_name, exec_triple, target_triple, channel = "stable"):
+    """Emits a toolchain for the rust_toolchain in another workspace.
+
+    Args:
+      name: The name of the toolchain, and of the rust_toolchain with an `_impl` suffix
+      parent_workspace_name: The name of the workspace of the rust_toolchain
+      exec_triple: The rust-style target that this compiler runs on
+      target_triple: The rust-style target triple of the toolchain
+      channel: The release channel of the toolchain, which is only used for platforms with the
+               matching `@io_bazel_rules_rust//rust/settings:channel`
+    """
+    target_constraint_set = triple_to_toolchain_target_constraint_set(target_triple, exec_triple)
+    target_constraint_set.append("@io_bazel_rules_rust//rust/settings:" + channel)
     return """
 toolchain(
     name = "{name}",
@@ -206,8 +477,14 @@ toolchain(
 )
 """.format(
         name = name,
-        exec_constraint_sets_serialized = serialized_constraint_set_from_triple(exec_triple),
-        target_constraint_sets_serialized = serialized_constraint_set_from_triple(target_triple),
+        exec_constraint_sets_serialized = "[{}]".format(", ".join([
+            "\"{}\"".format(constraint)
+            for constraint in triple_to_exec_constraint_set(exec_triple)
+        ])),
+        target_constraint_sets_serialized = "[{}]".format(", ".join([
+            "\"{}\"".format(constraint)
+            for constraint in target_constraint_set
+        ])),
         parent_workspace_name = parent_workspace_name,
     )
 
@@ -216,33 +493,53 @@ def produce_tool_suburl(tool_name, target_triple, version, iso_date = None):
 
     Args:
       tool_name: The name of the tool per static.rust-lang.org
-      target_triple: The rust-style target triple of the tool
+      target_triple: The rust-style target triple of the tool, or "" for tools which are the same
+                     for all targets, like rust-src.
       version: The version of the tool among "nightly", "beta', or an exact version.
       iso_date: The date of the tool (or None, if the version is a specific version).
     """
 
+    tool_path = produce_tool_path(tool_name, target_triple, version)
     if iso_date:
-        return "{}/{}-{}-{}".format(iso_date, tool_name, version, target_triple)
+        return "{}/{}".format(iso_date, tool_path)
     else:
-        return "{}-{}-{}".format(tool_name, version, target_triple)
+        return tool_path
 
 def produce_tool_path(tool_name, target_triple, version):
     """Produces a qualified Rust tool name
 
     Args:
       tool_name: The name of the tool per static.rust-lang.org
-      target_triple: The rust-style target triple of the tool
+      target_triple: The rust-style target triple of the tool, or "" for tools which are the same
+                     for all targets, like rust-src.
       version: The version of the tool among "nightly", "beta', or an exact version.
     """
 
+    if not target_triple:
+        return "{}-{}".format(tool_name, version)
     return "{}-{}-{}".format(tool_name, version, target_triple)
 
-def load_arbitrary_tool(ctx, tool_name, param_prefix, tool_subdirectory, version, iso_date, target_triple, sha256 = ""):
+def load_arbitrary_tool(
+        ctx,
+        tool_name,
+        param_prefix,
+        tool_subdirectory,
+        version,
+        iso_date,
+        target_triple,
+        sha256 = "",
+        urls = DEFAULT_STATIC_RUST_URL_TEMPLATES,
+        sha256s = {},
+        strict_checksums = False):
     """Loads a Rust tool, downloads, and extracts into the common workspace.
 
     This function sources the tool from the Rust-lang static file server. The index is available
     at: https://static.rust-lang.org/dist/index.html (or the path specified by
-    "${RUST_STATIC_URL}/dist/index.html" if the RUST_STATIC_URL envinronment variable is set).
+    "${STATIC_RUST_URL}/dist/index.html" if the STATIC_RUST_URL envinronment variable is set).
+    The server can be replaced or mirrored with `urls`, which are tried in order.
+
+    The archive is verified against its checksum in `sha256s`, or else the one in
+    @io_bazel_rules_rust//rust:known_shas.bzl, or else `sha256`.
 
     Args:
       ctx: A repository_ctx (no attrs required).
@@ -253,7 +550,14 @@ def load_arbitrary_tool(ctx, tool_name, param_prefix, tool_subdirectory, version
                          $TOOL_NAME-$VERSION-$TARGET_TRIPLE.
       version: The version of the tool among "nightly", "beta', or an exact version.
       iso_date: The date of the tool (or None, if the version is a specific version).
-      target_triple: The rust-style target triple of the tool
+      target_triple: The rust-style target triple of the tool, or "" for tools which are the same
+                     for all targets, like rust-src.
+      sha256: The checksum of the archive, if it is neither in `sha256s` nor known.
+      urls: The templates of the URLs to download the archive from, in which `{}` is replaced by
+            the path of the archive below `dist`, eg. `file:///mnt/rust-dist/{}.tar.gz`.
+      sha256s: Checksums of archives, keyed by their path below `dist` without the extension, as
+               in @io_bazel_rules_rust//rust:known_shas.bzl.
+      strict_checksums: Whether to fail rather than download an archive with no checksum.
     """
 
     _check_version_valid(version, iso_date, param_prefix)
@@ -261,22 +565,28 @@ def load_arbitrary_tool(ctx, tool_name, param_prefix, tool_subdirectory, version
     # N.B. See https://static.rust-lang.org/dist/index.html to find the tool_suburl for a given
     # tool.
     tool_suburl = produce_tool_suburl(tool_name, target_triple, version, iso_date)
-    static_rust = ctx.os.environ["STATIC_RUST_URL"] if "STATIC_RUST_URL" in ctx.os.environ else "https://static.rust-lang.org"
-    url = "{}/dist/{}.tar.gz".format(static_rust, tool_suburl)
+    if "STATIC_RUST_URL" in ctx.os.environ:
+        urls = [ctx.os.environ["STATIC_RUST_URL"] + "/dist/{}.tar.gz"] + urls
+
+    tool_sha256 = sha256s.get(tool_suburl) or FILE_KEY_TO_SHA.get(tool_suburl) or sha256
+    if strict_checksums and not tool_sha256:
+        fail("The checksum of {} is unknown, and strict_checksums is set. Add it to sha256s.".format(
+            tool_suburl,
+        ))
 
     tool_path = produce_tool_path(tool_name, target_triple, version)
     ctx.download_and_extract(
-        url,
+        [url.format(tool_suburl) for url in urls],
         output = "",
-        sha256 = FILE_KEY_TO_SHA.get(tool_suburl) or sha256,
+        sha256 = tool_sha256,
         stripPrefix = "{}/{}".format(tool_path, tool_subdirectory),
     )
 
-def _load_rustfmt(ctx):
+def _load_rustfmt(ctx, config):
     target_triple = ctx.attr.exec_triple
 
-    if ctx.attr.rustfmt_version in ("beta", "nightly"):
-        iso_date = ctx.attr.iso_date
+    if config.rustfmt_version in ("beta", "nightly"):
+        iso_date = config.iso_date
     else:
         iso_date = None
 
@@ -287,16 +597,20 @@ def _load_rustfmt(ctx):
         target_triple = target_triple,
         tool_name = "rustfmt",
         tool_subdirectory = "rustfmt-preview",
-        version = ctx.attr.rustfmt_version,
+        version = config.rustfmt_version,
+        urls = ctx.attr.urls,
+        sha256s = ctx.attr.sha256s,
+        strict_checksums = ctx.attr.strict_checksums,
     )
 
     return BUILD_for_rustfmt(target_triple)
 
-def _load_rust_compiler(ctx):
+def _load_rust_compiler(ctx, config):
     """Loads a rust compiler and yields corresponding BUILD for it
 
     Args:
       ctx: A repository_ctx.
+      config: The versions of the toolchain, from `_toolchain_config`.
     Returns:
       The BUILD file contents for this compiler and compiler library
     """
@@ -304,36 +618,112 @@ def _load_rust_compiler(ctx):
     target_triple = ctx.attr.exec_triple
     load_arbitrary_tool(
         ctx,
-        iso_date = ctx.attr.iso_date,
+        iso_date = config.iso_date,
         param_prefix = "rustc_",
         target_triple = target_triple,
         tool_name = "rustc",
         tool_subdirectory = "rustc",
-        version = ctx.attr.version,
+        version = config.version,
+        urls = ctx.attr.urls,
+        sha256s = ctx.attr.sha256s,
+        strict_checksums = ctx.attr.strict_checksums,
     )
 
     compiler_BUILD = BUILD_for_compiler(target_triple)
 
     return compiler_BUILD
 
-def _load_rust_stdlib(ctx, target_triple):
+def _load_llvm_tools(ctx, config):
+    """Loads the LLVM tools shipped with rustc, such as llvm-objcopy, and yields the BUILD for them
+
+    Args:
+      ctx: A repository_ctx.
+      config: The versions of the toolchain, from `_toolchain_config`.
+    Returns:
+      The BUILD file contents for the LLVM tools
+    """
+
+    target_triple = ctx.attr.exec_triple
+    load_arbitrary_tool(
+        ctx,
+        iso_date = config.iso_date,
+        param_prefix = "llvm-tools_",
+        target_triple = target_triple,
+        tool_name = "llvm-tools",
+        tool_subdirectory = "llvm-tools-preview",
+        version = config.version,
+        urls = ctx.attr.urls,
+        sha256s = ctx.attr.sha256s,
+        strict_checksums = ctx.attr.strict_checksums,
+    )
+
+    return BUILD_for_llvm_tools(target_triple)
+
+# The optional components of a toolchain repository besides rustfmt and the LLVM tools, with the
+# names of their archives, the directories of their files within them, and whether there is an
+# archive for each host.
+_OPTIONAL_COMPONENTS = {
+    "cargo": struct(tool_name = "cargo", tool_subdirectory = "cargo", per_host = True),
+    "clippy": struct(tool_name = "clippy", tool_subdirectory = "clippy-preview", per_host = True),
+    "rust-src": struct(tool_name = "rust-src", tool_subdirectory = "rust-src", per_host = False),
+}
+
+def _load_component(ctx, config, component):
+    """Loads one of `_OPTIONAL_COMPONENTS`, such as cargo, and yields the BUILD for it
+
+    Args:
+      ctx: A repository_ctx.
+      config: The versions of the toolchain, from `_toolchain_config`.
+      component: The name of the component.
+    Returns:
+      The BUILD file contents for the component
+    """
+
+    target_triple = ctx.attr.exec_triple
+    archive = _OPTIONAL_COMPONENTS[component]
+    load_arbitrary_tool(
+        ctx,
+        iso_date = config.iso_date,
+        param_prefix = component + "_",
+        target_triple = target_triple if archive.per_host else "",
+        tool_name = archive.tool_name,
+        tool_subdirectory = archive.tool_subdirectory,
+        version = config.version,
+        urls = ctx.attr.urls,
+        sha256s = ctx.attr.sha256s,
+        strict_checksums = ctx.attr.strict_checksums,
+    )
+
+    if component == "cargo":
+        return BUILD_for_cargo(target_triple)
+    elif component == "clippy":
+        return BUILD_for_clippy(target_triple)
+    else:
+        return BUILD_for_rust_src()
+
+def _load_rust_stdlib(ctx, config, target_triple, include_llvm_tools = False):
     """Loads a rust standard library and yields corresponding BUILD for it
 
     Args:
       ctx: A repository_ctx.
+      config: The versions of the toolchain, from `_toolchain_config`.
       target_triple: The rust-style target triple of the tool
+      include_llvm_tools: Whether the LLVM tools were loaded into the repository
     Returns:
       The BUILD file contents for this stdlib, and a toolchain decl to match
     """
 
     load_arbitrary_tool(
         ctx,
-        iso_date = ctx.attr.iso_date,
+        iso_date = config.iso_date,
         param_prefix = "rust-std_",
         target_triple = target_triple,
         tool_name = "rust-std",
         tool_subdirectory = "rust-std-{}".format(target_triple),
-        version = ctx.attr.version,
+        version = config.version,
+        urls = ctx.attr.urls,
+        sha256s = ctx.attr.sha256s,
+        strict_checksums = ctx.attr.strict_checksums,
     )
 
     toolchain_prefix = ctx.attr.toolchain_name_prefix or DEFAULT_TOOLCHAIN_NAME_PREFIX
@@ -347,29 +737,204 @@ def _load_rust_stdlib(ctx, target_triple):
         target_triple = target_triple,
         workspace_name = ctx.attr.name,
         default_edition = ctx.attr.edition,
+        include_llvm_tools = include_llvm_tools,
+        components = config.components,
     )
 
     return stdlib_BUILD + toolchain_BUILD
 
+# The components rustup installs, which may be listed in a rust-toolchain file. Of these, rustfmt,
+# the LLVM tools and `_OPTIONAL_COMPONENTS` are loaded into toolchain repositories, and the rest
+# are ignored.
+_RUSTUP_COMPONENTS = [
+    "cargo",
+    "clippy",
+    "clippy-preview",
+    "llvm-tools",
+    "llvm-tools-preview",
+    "miri",
+    "miri-preview",
+    "rls",
+    "rls-preview",
+    "rust-analysis",
+    "rust-analyzer",
+    "rust-analyzer-preview",
+    "rust-docs",
+    "rust-src",
+    "rust-std",
+    "rustc",
+    "rustc-dev",
+    "rustfmt",
+    "rustfmt-preview",
+]
+
+def parse_rust_toolchain_file(content):
+    """Parses a rust-toolchain or rust-toolchain.toml file, as used by rustup.
+
+    The file is either the TOML format, with a `[toolchain]` table, or the legacy format, which is
+    only the name of the channel.
+
+    Args:
+      content: The content of the file.
+
+    Returns:
+      struct: with the `channel`, the lists of `components` and `targets`, and the `path` of a
+              toolchain which is already installed, or None.
+    """
+    if "[toolchain]" not in content:
+        return struct(channel = content.strip(), components = [], targets = [], path = None)
+
+    # The keys of the `[toolchain]` table are strings, or arrays of them, which may span lines.
+    toolchain = {}
+    in_toolchain = False
+    key = None
+    value = ""
+    for line in content.splitlines():
+        line = line.split("#")[0].strip()
+        if not line:
+            continue
+        if key:
+            value += line
+        elif line.startswith("["):
+            in_toolchain = line == "[toolchain]"
+            continue
+        elif in_toolchain and "=" in line:
+            key, _, value = line.partition("=")
+            key = key.strip()
+            value = value.strip()
+        else:
+            continue
+        if value.startswith("[") and not value.endswith("]"):
+            continue
+        if value.startswith("["):
+            toolchain[key] = [v.strip().strip("\"'") for v in value[1:-1].split(",") if v.strip()]
+        else:
+            toolchain[key] = value.strip("\"'")
+        key = None
+
+    if "channel" not in toolchain and "path" not in toolchain:
+        fail("The [toolchain] of the rust-toolchain file has no channel")
+    return struct(
+        channel = toolchain.get("channel"),
+        components = toolchain.get("components", []),
+        targets = toolchain.get("targets", []),
+        path = toolchain.get("path"),
+    )
+
+def _read_rust_toolchain_file(ctx):
+    """Parses the `rust_toolchain_file` of a repository, which must name a channel to download."""
+    rust_toolchain = parse_rust_toolchain_file(ctx.read(ctx.attr.rust_toolchain_file))
+    if rust_toolchain.path:
+        fail("Toolchains at a path are not downloaded. Use rust_local_repository_set instead")
+    return rust_toolchain
+
+def _toolchain_config(ctx):
+    """Returns the versions and targets of a toolchain repository.
+
+    These are given by the attributes of the repository, or its `rust_toolchain_file`.
+
+    Returns:
+      struct: with the `version`, `iso_date` and `rustfmt_version` of the toolchain, its
+              `extra_target_triples`, whether it `include_llvm_tools`, and the other
+              `components` to load, among `_OPTIONAL_COMPONENTS`.
+    """
+    for component in ctx.attr.components:
+        if component not in _OPTIONAL_COMPONENTS and component != "llvm-tools-preview":
+            fail("Unknown component \"{}\". Expected one of: {}".format(
+                component,
+                ", ".join(sorted(_OPTIONAL_COMPONENTS.keys() + ["llvm-tools-preview"])),
+            ))
+
+    if not ctx.attr.rust_toolchain_file:
+        if not ctx.attr.version:
+            fail("Either version or rust_toolchain_file must be set")
+        version = ctx.attr.version
+        iso_date = ctx.attr.iso_date
+        rustfmt_version = ctx.attr.rustfmt_version
+        components = list(ctx.attr.components)
+        extra_target_triples = ctx.attr.extra_target_triples
+    else:
+        if ctx.attr.version or ctx.attr.iso_date or ctx.attr.rustfmt_version:
+            fail("version, iso_date and rustfmt_version are given by rust_toolchain_file, and must not be set")
+        rust_toolchain = _read_rust_toolchain_file(ctx)
+
+        # The channel is an exact version, or a dated beta or nightly, eg. `nightly-2020-04-19`.
+        version, _, iso_date = rust_toolchain.channel.partition("-")
+        if version not in ("beta", "nightly") and (iso_date or len(version.split(".")) != 3):
+            fail("The channel in {} must be an exact version, or a dated beta or nightly, not \"{}\"".format(
+                ctx.attr.rust_toolchain_file,
+                rust_toolchain.channel,
+            ))
+
+        for component in rust_toolchain.components:
+            if component not in _RUSTUP_COMPONENTS:
+                fail("Unknown component \"{}\" in {}".format(component, ctx.attr.rust_toolchain_file))
+
+        # rustup still accepts the names components had while they were previews.
+        components = list(ctx.attr.components)
+        for component in rust_toolchain.components:
+            component = {"clippy-preview": "clippy", "llvm-tools": "llvm-tools-preview"}.get(component, component)
+            if component not in components:
+                components.append(component)
+        rustfmt_version = version if "rustfmt" in components or "rustfmt-preview" in components else None
+
+        extra_target_triples = list(ctx.attr.extra_target_triples)
+        for target_triple in rust_toolchain.targets:
+            if target_triple != ctx.attr.exec_triple and target_triple not in extra_target_triples:
+                extra_target_triples.append(target_triple)
+
+    _check_version_valid(version, iso_date)
+
+    # The firmware of bare-metal targets is turned into images by `rust_binary_objcopy`.
+    target_triples = [ctx.attr.exec_triple] + extra_target_triples
+    include_llvm_tools = (
+        "none" in [triple_to_system(triple) for triple in target_triples] or
+        "llvm-tools-preview" in components
+    )
+
+    return struct(
+        version = version,
+        iso_date = iso_date or None,
+        rustfmt_version = rustfmt_version,
+        extra_target_triples = extra_target_triples,
+        include_llvm_tools = include_llvm_tools,
+        components = [component for component in components if component in _OPTIONAL_COMPONENTS],
+    )
+
 def _rust_toolchain_repository_impl(ctx):
     """The implementation of the rust toolchain repository rule."""
 
-    _check_version_valid(ctx.attr.version, ctx.attr.iso_date)
+    config = _toolchain_config(ctx)
+
+    BUILD_components = [_load_rust_compiler(ctx, config)]
 
-    BUILD_components = [_load_rust_compiler(ctx)]
+    if config.rustfmt_version:
+        BUILD_components.append(_load_rustfmt(ctx, config))
 
-    if ctx.attr.rustfmt_version:
-        BUILD_components.append(_load_rustfmt(ctx))
+    if config.include_llvm_tools:
+        BUILD_components.append(_load_llvm_tools(ctx, config))
 
-    for target_triple in [ctx.attr.exec_triple] + ctx.attr.extra_target_triples:
-        BUILD_components.append(_load_rust_stdlib(ctx, target_triple))
+    for component in config.components:
+        BUILD_components.append(_load_component(ctx, config, component))
+
+    for target_triple in [ctx.attr.exec_triple] + config.extra_target_triples:
+        BUILD_components.append(_load_rust_stdlib(ctx, config, target_triple, config.include_llvm_tools))
 
     ctx.file("WORKSPACE", "")
     ctx.file("BUILD", "\n".join(BUILD_components))
 
 def _rust_toolchain_repository_proxy_impl(ctx):
+    channel = version_to_channel(ctx.attr.version)
+    extra_target_triples = list(ctx.attr.extra_target_triples)
+    if ctx.attr.rust_toolchain_file:
+        rust_toolchain = _read_rust_toolchain_file(ctx)
+        channel = version_to_channel(rust_toolchain.channel)
+        for target_triple in rust_toolchain.targets:
+            if target_triple != ctx.attr.exec_triple and target_triple not in extra_target_triples:
+                extra_target_triples.append(target_triple)
+
     BUILD_components = []
-    for target_triple in [ctx.attr.exec_triple] + ctx.attr.extra_target_triples:
+    for target_triple in [ctx.attr.exec_triple] + extra_target_triples:
         BUILD_components.append(BUILD_for_toolchain(
             name = "{toolchain_prefix}_{target_triple}".format(
                 toolchain_prefix = ctx.attr.toolchain_name_prefix,
@@ -378,7 +943,9 @@ def _rust_toolchain_repository_proxy_impl(ctx):
             exec_triple = ctx.attr.exec_triple,
             parent_workspace_name = ctx.attr.parent_workspace_name,
             target_triple = target_triple,
+            channel = channel,
         ))
+    BUILD_components.append(BUILD_for_bare_metal_cc_toolchain([ctx.attr.exec_triple] + extra_target_triples))
 
     ctx.file("WORKSPACE", "")
     ctx.file("BUILD", "\n".join(BUILD_components))
@@ -395,21 +962,41 @@ Args:
   version: The version of the tool among "nightly", "beta', or an exact version.
   rustfmt_version: The version of rustfmt to be associated with the toolchain.
   iso_date: The date of the tool (or None, if the version is a specific version).
+  rust_toolchain_file: A `rust-toolchain` or `rust-toolchain.toml` file, as used by rustup and
+                       Cargo, instead of `version`, `iso_date` and `rustfmt_version`. The version
+                       and date are those of its `channel`, rustfmt and the LLVM tools are loaded
+                       if they are among its `components`, and its `targets` are added to
+                       `extra_target_triples`.
+  components: Other components to load with rustc, among "cargo", "clippy", "llvm-tools-preview"
+              and "rust-src", whose files are the `cargo`, `clippy_driver`, `llvm_objcopy`,
+              `llvm_size`, `llvm_tools` and `rust_src` of the rust_toolchains. Their archives are
+              checksummed like the others.
   exec_triple: The Rust-style target triple for the compilation platform
   extra_target_triples: The Rust-style triples for extra compilation targets
   toolchain_name_prefix: The per-target prefix expected for the rust_toolchain declarations
   edition: The rust edition to be used by default (2015 (default) or 2018)
+  urls: The templates of the URLs to download the archives from, tried in order, in which `{}` is
+        replaced by the path of an archive below `dist`, eg. `rustc-1.39.0-x86_64-unknown-linux-gnu`.
+        `file://` URLs can point at a local copy of the `dist` directory.
+  sha256s: Checksums of the archives, keyed by their path below `dist` without the extension, in
+           addition to those in @io_bazel_rules_rust//rust:known_shas.bzl.
+  strict_checksums: Whether to fail rather than download an archive whose checksum is unknown.
 """
 
 rust_toolchain_repository = repository_rule(
     attrs = {
-        "version": attr.string(mandatory = True),
+        "version": attr.string(),
         "rustfmt_version": attr.string(),
         "iso_date": attr.string(),
+        "rust_toolchain_file": attr.label(allow_single_file = True),
+        "components": attr.string_list(),
         "exec_triple": attr.string(mandatory = True),
         "extra_target_triples": attr.string_list(),
         "toolchain_name_prefix": attr.string(),
         "edition": attr.string(default = "2015"),
+        "urls": attr.string_list(default = DEFAULT_STATIC_RUST_URL_TEMPLATES),
+        "sha256s": attr.string_dict(),
+        "strict_checksums": attr.bool(default = False),
     },
     implementation = _rust_toolchain_repository_impl,
 )
@@ -422,6 +1009,11 @@ Args:
   parent_workspace_name: The name of the other rust_toolchain_repository
   exec_triple: The Rust-style target triple for the compilation platform
   extra_target_triples: The Rust-style triples for extra compilation targets
+  version: The `version` of the other rust_toolchain_repository, whose channel the toolchains are
+           declared for.
+  rust_toolchain_file: The `rust_toolchain_file` of the other rust_toolchain_repository, whose
+                       `targets` are added to `extra_target_triples`, and whose `channel` is used
+                       instead of `version`.
   toolchain_name_prefix: The per-target prefix expected for the rust_toolchain declarations in the
                          parent workspace.
 """
@@ -431,19 +1023,457 @@ rust_toolchain_repository_proxy = repository_rule(
         "parent_workspace_name": attr.string(mandatory = True),
         "exec_triple": attr.string(mandatory = True),
         "extra_target_triples": attr.string_list(),
+        "version": attr.string(),
+        "rust_toolchain_file": attr.label(allow_single_file = True),
         "toolchain_name_prefix": attr.string(),
     },
     implementation = _rust_toolchain_repository_proxy_impl,
 )
 
+def _installed_target_triples(rustlib):
+    """Returns the triples whose standard library is installed in a sysroot's `lib/rustlib`."""
+    triples = []
+    for target_dir in rustlib.readdir():
+        lib_dir = target_dir.get_child("lib")
+        if not lib_dir.exists:
+            continue
+        if [f for f in lib_dir.readdir() if f.basename.startswith("libcore-")]:
+            triples.append(target_dir.basename)
+    return triples
+
+def _rust_local_toolchain_repository_impl(ctx):
+    """The implementation of the local rust toolchain repository rule."""
+
+    binary_ext = ".exe" if ctx.os.name.lower().startswith("windows") else ""
+    sysroot = ctx.attr.sysroot
+    if not sysroot:
+        rustc = ctx.which("rustc")
+        if not rustc:
+            fail("No sysroot was given, and rustc is not on the PATH")
+        result = ctx.execute([rustc, "--print", "sysroot"])
+        if result.return_code != 0:
+            fail("Failed to print the sysroot of {}: {}".format(rustc, result.stderr))
+        sysroot = result.stdout.strip()
+    sysroot = ctx.path(sysroot)
+
+    rustc = sysroot.get_child("bin").get_child("rustc" + binary_ext)
+    if not rustc.exists:
+        fail("{} is not a Rust sysroot, as it has no bin/rustc{}".format(sysroot, binary_ext))
+
+    # The output has lines like `release: 1.39.0` and `host: x86_64-unknown-linux-gnu`.
+    result = ctx.execute([rustc, "-vV"])
+    if result.return_code != 0:
+        fail("Failed to print the version of {}: {}".format(rustc, result.stderr))
+    rustc_info = {}
+    for line in result.stdout.splitlines():
+        key, separator, value = line.partition(": ")
+        if separator:
+            rustc_info[key] = value
+    version = rustc_info["release"]
+    exec_triple = rustc_info["host"]
+
+    # Nightly and beta releases are eg. `1.41.0-nightly`.
+    expected_version = ctx.attr.version
+    if expected_version and version != expected_version and not version.endswith("-" + expected_version):
+        fail("The rustc in {} is version {}, but {} was expected".format(sysroot, version, expected_version))
+
+    rustlib = sysroot.get_child("lib").get_child("rustlib")
+    installed_triples = _installed_target_triples(rustlib)
+    if ctx.attr.target_triples:
+        for target_triple in ctx.attr.target_triples:
+            if target_triple not in installed_triples:
+                fail("The standard library for {} is not installed in {}".format(target_triple, sysroot))
+        target_triples = ctx.attr.target_triples
+    else:
+        # Targets rules_rust has no platform for can't be used anyway.
+        target_triples = sorted([t for t in installed_triples if t in SUPPORTED_PLATFORM_TRIPLES])
+    if exec_triple not in target_triples:
+        target_triples = [exec_triple] + target_triples
+
+    for entry in sysroot.readdir():
+        ctx.symlink(entry, entry.basename)
+
+    BUILD_components = [BUILD_for_compiler(exec_triple)]
+
+    include_rustfmt = sysroot.get_child("bin").get_child("rustfmt" + binary_ext).exists
+    if include_rustfmt:
+        BUILD_components.append(BUILD_for_rustfmt(exec_triple))
+
+    include_llvm_tools = rustlib.get_child(exec_triple).get_child("bin").get_child("llvm-objcopy" + binary_ext).exists
+    if include_llvm_tools:
+        BUILD_components.append(BUILD_for_llvm_tools(exec_triple))
+
+    # The components rustup installed alongside rustc.
+    components = []
+    if sysroot.get_child("bin").get_child("cargo" + binary_ext).exists:
+        components.append("cargo")
+        BUILD_components.append(BUILD_for_cargo(exec_triple))
+    if sysroot.get_child("bin").get_child("clippy-driver" + binary_ext).exists:
+        components.append("clippy")
+        BUILD_components.append(BUILD_for_clippy(exec_triple))
+    if rustlib.get_child("src").get_child("rust").exists:
+        components.append("rust-src")
+        BUILD_components.append(BUILD_for_rust_src())
+
+    toolchain_prefix = ctx.attr.toolchain_name_prefix or DEFAULT_TOOLCHAIN_NAME_PREFIX
+    toolchains_BUILD_components = []
+    for target_triple in target_triples:
+        toolchain_name = "{toolchain_prefix}_{target_triple}".format(
+            toolchain_prefix = toolchain_prefix,
+            target_triple = target_triple,
+        )
+        BUILD_components.append(BUILD_for_stdlib(target_triple))
+        BUILD_components.append(BUILD_for_rust_toolchain(
+            name = toolchain_name,
+            exec_triple = exec_triple,
+            target_triple = target_triple,
+            workspace_name = ctx.attr.name,
+            default_edition = ctx.attr.edition,
+            include_llvm_tools = include_llvm_tools,
+            include_rustfmt = include_rustfmt,
+            components = components,
+        ))
+        toolchains_BUILD_components.append(BUILD_for_toolchain(
+            name = toolchain_name,
+            exec_triple = exec_triple,
+            parent_workspace_name = ctx.attr.name,
+            target_triple = target_triple,
+            channel = version_to_channel(version),
+        ))
+    toolchains_BUILD_components.append(BUILD_for_bare_metal_cc_toolchain(target_triples))
+
+    ctx.file("WORKSPACE", "")
+    ctx.file("BUILD", "\n".join(BUILD_components))
+    ctx.file("toolchains/BUILD", "\n".join(toolchains_BUILD_components))
+    ctx.file("version.bzl", "RUSTC_VERSION = \"{}\"\n".format(version))
+
+"""Composes a workspace from a Rust toolchain which is already installed, such as one managed by
+rustup, instead of downloading one.
+
+The version of rustc and the triple it runs on are detected from the toolchain, and a
+rust_toolchain is declared for each target whose standard library is installed in the sysroot.
+The rustfmt, cargo, clippy, rust-src and LLVM tools components are included if they are installed.
+Their toolchain declarations are in the `toolchains` package of the workspace, to be registered
+with `register_toolchains("@<name>//toolchains:all")`, as `rust_local_repository_set` does. The
+version of rustc is available to Starlark as `RUSTC_VERSION` in `@<name>//:version.bzl`.
+
+Args:
+  name: A unique name for this rule
+  sysroot: The sysroot of the toolchain, such as `/opt/rust-1.39.0`. By default, the sysroot of
+           the rustc on the PATH, as printed by `rustc --print sysroot`.
+  version: The version the toolchain is expected to be, among "nightly", "beta", or an exact
+           version. The repository fails to build if it is another version.
+  target_triples: The Rust-style triples to declare toolchains for, which must be installed. By
+                  default, all installed triples with a platform in
+                  `@io_bazel_rules_rust//rust/platform/triples`.
+  toolchain_name_prefix: The per-target prefix for the rust_toolchain declarations
+  edition: The rust edition to be used by default (2015 (default) or 2018)
+"""
+
+rust_local_toolchain_repository = repository_rule(
+    attrs = {
+        "sysroot": attr.string(),
+        "version": attr.string(),
+        "target_triples": attr.string_list(),
+        "toolchain_name_prefix": attr.string(),
+        "edition": attr.string(default = "2015"),
+    },
+    environ = ["PATH", "RUSTUP_HOME", "RUSTUP_TOOLCHAIN"],
+    local = True,
+    implementation = _rust_local_toolchain_repository_impl,
+)
+
+def _host_triple(ctx):
+    """Returns the triple of the machine Bazel runs on, as used for the exec_triple of toolchains.
+
+    Returns None if there is no Rust host triple for the machine.
+    """
+    os = ctx.os.name.lower()
+    if os.startswith("windows"):
+        return "x86_64-pc-windows-msvc"
+
+    arch = ctx.execute(["uname", "-m"]).stdout.strip()
+    arch = {"amd64": "x86_64", "arm64": "aarch64"}.get(arch, arch)
+    if os == "linux":
+        return "{}-unknown-linux-gnu".format(arch)
+    elif os.startswith("mac"):
+        return "{}-apple-darwin".format(arch)
+    elif os == "freebsd":
+        return "{}-unknown-freebsd".format(arch)
+    return None
+
+def BUILD_for_cfg_setting(name, triples):
+    """Emits a setting which matches the platform of any of `triples`.
+
+    `select` fails if more than one of its conditions match, so this is a chain of aliases, each
+    of which is either a triple's config_setting if it matches, or the next alias.
+    """
+    BUILD = ""
+    for i, triple in enumerate(triples):
+        alias_name = name if i == 0 else "_{}_{}".format(name, i)
+        if i == len(triples) - 1:
+            actual = "\":{}\"".format(triple)
+        else:
+            actual = """select({{
+        ":{triple}": ":{triple}",
+        "//conditions:default": ":{next}",
+    }})""".format(triple = triple, next = "_{}_{}".format(name, i + 1))
+        BUILD += """
+alias(
+    name = "{name}",
+    actual = {actual},{visibility}
+)
+""".format(
+            name = alias_name,
+            actual = actual,
+            visibility = "" if i == 0 else "\n    visibility = [\"//visibility:private\"],",
+        )
+    return BUILD
+
+def _rust_target_cfg_repository_impl(ctx):
+    host_triple = _host_triple(ctx)
+    rustc = None
+    for label, exec_triple in ctx.attr.rustcs.items():
+        if exec_triple == host_triple:
+            rustc = ctx.path(label)
+    if not rustc:
+        fail((
+            "{} runs rustc to list the cfg options of its targets, but none of its rustcs runs on " +
+            "this host ({}). Add the rustc of a toolchain for the host to its `rustcs`, eg. " +
+            "`\"@<repository>//:bin/rustc\": \"<host triple>\"` for a rust_repository_set " +
+            "with that exec_triple."
+        ).format(ctx.name, host_triple or ctx.os.name))
+
+    # A config_setting matches every platform which has its constraints, so the setting of a triple
+    # whose constraints are a subset of another's, eg. x86_64-unknown-linux-gnu, whose GNU ABI is
+    # the default, and x86_64-unknown-linux-musl, would also match the platforms of the other.
+    constraint_sets = {triple: triple_to_constraint_set(triple) for triple in ctx.attr.target_triples}
+    for triple, constraint_set in constraint_sets.items():
+        for other, other_constraint_set in constraint_sets.items():
+            if other != triple and all([c in other_constraint_set for c in constraint_set]):
+                fail((
+                    "{} can't tell the platforms of {} and {} apart, as the constraints of the " +
+                    "first are a subset of those of the second. Use a separate " +
+                    "rust_target_cfg_repository for each."
+                ).format(ctx.name, triple, other))
+
+    # The triples each cfg applies to, keyed by eg. `target_os=linux` or `unix`.
+    triples_by_cfg = {}
+    BUILD_components = ["package(default_visibility = [\"//visibility:public\"])\n"]
+    for triple in ctx.attr.target_triples:
+        result = ctx.execute([rustc, "--print", "cfg", "--target", triple])
+        if result.return_code != 0:
+            fail("Failed to print the cfg of {}: {}".format(triple, result.stderr))
+        for line in result.stdout.splitlines():
+            # This depends on the compilation mode rather than the target.
+            if not line or line == "debug_assertions":
+                continue
+            triples_by_cfg.setdefault(line.replace("\"", ""), []).append(triple)
+
+        BUILD_components.append("""
+config_setting(
+    name = "{triple}",
+    constraint_values = {constraint_set},
+    visibility = ["//visibility:private"],
+)
+""".format(
+            triple = triple,
+            constraint_set = serialized_constraint_set_from_triple(triple),
+        ))
+
+    for cfg in sorted(triples_by_cfg.keys()):
+        BUILD_components.append(BUILD_for_cfg_setting(cfg, triples_by_cfg[cfg]))
+
+    ctx.file("WORKSPACE", "")
+    ctx.file("BUILD", "".join(BUILD_components))
+
+"""Generates a config_setting for each cfg option rustc sets for the given targets, for use in
+`select`.
+
+rustc is run with `--print cfg` for each target triple, and a setting is generated for each
+option it prints, which matches the platforms of the triples which have that option. The
+settings are named after the options, eg. `@rust_cfg//:target_os=linux`,
+`@rust_cfg//:target_feature=sse2` or `@rust_cfg//:unix`, so that a `select` can mirror a
+`#[cfg(...)]`.
+
+Platforms are matched by the constraint values of the triples, including their ABI, such as
+`@io_bazel_rules_rust//rust/platform/abi:musl`. The default ABI of a CPU and system has no
+constraint, so that the host platform matches it, which means that a triple with the default ABI
+matches the platforms of the triples with other ABIs for its CPU and system too. Those can't be
+given to the same repository, so there should be one for each ABI, eg. one with
+`x86_64-unknown-linux-gnu` and one with `x86_64-unknown-linux-musl`.
+
+Args:
+  name: A unique name for this rule
+  rustcs: The rustc of each toolchain repository, mapped to the exec triple it runs on. The one
+          for the host is used, so there must be one for each host Bazel runs on.
+  target_triples: The Rust-style triples to generate settings for.
+"""
+
+rust_target_cfg_repository = repository_rule(
+    attrs = {
+        "rustcs": attr.label_keyed_string_dict(
+            allow_files = True,
+            mandatory = True,
+        ),
+        "target_triples": attr.string_list(mandatory = True),
+    },
+    implementation = _rust_target_cfg_repository_impl,
+)
+
+def _file_key(path):
+    """Returns the key of an archive in FILE_KEY_TO_SHA, from its path below `dist` without `.tar.gz`.
+
+    Stable archives are downloaded from the top of `dist`, so their keys have no dated directory,
+    unlike those of beta and nightly archives.
+    """
+    file_name = path.rpartition("/")[2]
+    if "-beta" in file_name or "-nightly" in file_name:
+        return path
+    return file_name
+
+def manifest_checksums(content):
+    """Returns the checksums of the archives in a channel manifest, eg. `channel-rust-1.39.0.toml`.
+
+    The archives of each package are in `[pkg.<package>.target.<triple>]` tables, whose `url` and
+    `hash` are those of the `.tar.gz`. Stable archives are keyed by their file name, and beta and
+    nightly ones by their path below `dist`, with their dated directory, as `load_arbitrary_tool`
+    downloads them.
+
+    Args:
+      content: The contents of the manifest.
+
+    Returns:
+      dict: The checksums, keyed like FILE_KEY_TO_SHA.
+    """
+    checksums = {}
+    target = None
+
+    # Each table ends where the next begins, or at the end of the file.
+    for line in content.splitlines() + ["["]:
+        line = line.strip()
+        if line.startswith("["):
+            if target and target.get("available") == "true" and target.get("url") and target.get("hash"):
+                path = target["url"].partition("/dist/")[2]
+                if path.endswith(".tar.gz"):
+                    checksums[_file_key(path[:-len(".tar.gz")])] = target["hash"]
+            header = line.strip("[]").split(".", 3)
+            is_target = not line.startswith("[[") and len(header) == 4 and header[0] == "pkg" and header[2] == "target"
+            target = {} if is_target else None
+        elif target != None and "=" in line:
+            key, _, value = line.partition("=")
+            target[key.strip()] = value.strip().strip("\"")
+    return checksums
+
+def _rust_toolchain_checksums_impl(ctx):
+    """The implementation of the rust_toolchain_checksums repository rule."""
+
+    checksums = {}
+
+    for manifest in ctx.attr.manifests:
+        for manifest_file in manifest.files.to_list():
+            checksums.update(manifest_checksums(ctx.read(manifest_file)))
+
+    if ctx.attr.dist_dir:
+        dist_dir = ctx.attr.dist_dir
+        if not dist_dir.startswith("/"):
+            dist_dir = "{}/{}".format(ctx.path(Label("@//:WORKSPACE")).dirname, dist_dir)
+        dist_dir = str(ctx.path(dist_dir))
+
+        sha256sum = ctx.which("sha256sum")
+        if sha256sum:
+            sha256sum = [sha256sum]
+        elif ctx.which("shasum"):
+            sha256sum = [ctx.which("shasum"), "-a", "256"]
+        else:
+            fail("Neither sha256sum nor shasum is on the PATH, to compute the checksums of {}".format(dist_dir))
+
+        # Each line is the checksum and path of an archive, eg.
+        # `<sha256>  /mnt/rust-dist/2020-04-19/rustc-nightly-x86_64-unknown-linux-gnu.tar.gz`.
+        result = ctx.execute(["find", "-L", dist_dir, "-name", "*.tar.gz", "-exec"] + sha256sum + ["{}", "+"])
+        if result.return_code != 0:
+            fail("Failed to compute the checksums of the archives in {}: {}".format(dist_dir, result.stderr))
+        for line in result.stdout.splitlines():
+            sha256, _, path = line.partition(" ")
+
+            # The path is marked with a `*` if the archive was read in binary mode.
+            path = path.lstrip(" *")
+            checksums[_file_key(path[len(dist_dir) + 1:-len(".tar.gz")])] = sha256
+
+    lines = ["# This is a generated file -- see rust_toolchain_checksums in @io_bazel_rules_rust//rust:repositories.bzl"]
+    lines.append("FILE_KEY_TO_SHA = {")
+    for file_key in sorted(checksums.keys()):
+        lines.append("    \"{}\": \"{}\",".format(file_key, checksums[file_key]))
+    lines.append("}")
+
+    ctx.file("WORKSPACE", "")
+    ctx.file("BUILD", "exports_files([\"known_shas.bzl\"])\n")
+    ctx.file("known_shas.bzl", "\n".join(lines) + "\n")
+
+"""Generates the checksums of Rust toolchain archives without downloading them, in place of those
+in @io_bazel_rules_rust//rust:known_shas.bzl, which util/fetch_shas.sh fetches from
+static.rust-lang.org.
+
+The checksums are computed from a local copy of the `dist` directory of static.rust-lang.org, as
+also used by the `urls` of the toolchain repositories, and read from channel manifests, such as
+`channel-rust-1.39.0.toml` or `channel-rust-nightly.toml`. They are written to `FILE_KEY_TO_SHA`
+in `@<name>//:known_shas.bzl`, in the format of @io_bazel_rules_rust//rust:known_shas.bzl, to be
+given as the `sha256s` of `rust_repositories` or `rust_repository_set`, which take precedence over
+the built-in ones:
+
+```python
+load("@io_bazel_rules_rust//rust:repositories.bzl", "rust_toolchain_checksums")
+
+rust_toolchain_checksums(
+    name = "rust_checksums",
+    dist_dir = "/mnt/rust-dist",
+    manifests = ["//third_party/rust:channel-rust-nightly.toml"],
+)
+
+load("@rust_checksums//:known_shas.bzl", "FILE_KEY_TO_SHA")
+load("@io_bazel_rules_rust//rust:repositories.bzl", "rust_repositories")
+
+rust_repositories(
+    urls = ["file:///mnt/rust-dist/{}.tar.gz"],
+    sha256s = FILE_KEY_TO_SHA,
+    strict_checksums = True,
+)
+```
+
+Archives in `dist_dir` are hashed when the repository is fetched, so it must be refetched, eg. by
+`bazel sync --only=rust_checksums`, after archives are added.
+
+Args:
+  name: A unique name for this rule
+  dist_dir: A directory laid out like `dist`, with stable archives such as
+            `rustc-1.39.0-x86_64-unknown-linux-gnu.tar.gz` at the top, and beta and nightly ones in
+            dated directories such as `2020-04-19`. Stable archives may also be in the dated
+            directory of their release, as they are on static.rust-lang.org. Either absolute, or
+            relative to the workspace.
+  manifests: Channel manifests, whose archives' checksums are added to those from `dist_dir`.
+"""
+
+rust_toolchain_checksums = repository_rule(
+    attrs = {
+        "dist_dir": attr.string(),
+        "manifests": attr.label_list(allow_files = [".toml"]),
+    },
+    implementation = _rust_toolchain_checksums_impl,
+)
+
 def rust_repository_set(
         name,
-        version,
-        exec_triple,
+        version = None,
+        exec_triple = None,
         extra_target_triples = [],
         iso_date = None,
         rustfmt_version = None,
-        edition = None):
+        edition = None,
+        urls = DEFAULT_STATIC_RUST_URL_TEMPLATES,
+        sha256s = None,
+        strict_checksums = False,
+        rust_toolchain_file = None,
+        components = []):
     """Assembles a remote repository for the given toolchain params, produces a proxy repository
     to contain the toolchain declaration, and registers the toolchains.
 
@@ -452,15 +1482,30 @@ def rust_repository_set(
 
     Args:
       name: The name of the generated repository
-      version: The version of the tool among "nightly", "beta', or an exact version.
+      version: The version of the tool among "nightly", "beta', or an exact version, or None if
+               `rust_toolchain_file` is given.
       iso_date: The date of the tool (or None, if the version is a specific version).
-      exec_triple: The Rust-style target that this compiler runs on
+      exec_triple: The Rust-style target that this compiler runs on. Required.
       extra_target_triples: Additional rust-style targets that this set of toolchains
-                            should support.
+                            should support. Bare-metal targets, such as `thumbv7em-none-eabihf`,
+                            only have `core` and `alloc`, and are linked by rust-lld. The LLVM
+                            tools used by `rust_binary_objcopy` are included with them.
       rustfmt_version: The version of rustfmt to be associated with the toolchain.
       edition: The rust edition to be used by default (2015 (default) or 2018)
+      urls: The templates of the URLs to download the archives from, tried in order. See
+            `rust_toolchain_repository`.
+      sha256s: Checksums of the archives, in addition to the known ones.
+      strict_checksums: Whether to fail rather than download an archive whose checksum is unknown.
+      rust_toolchain_file: A `rust-toolchain` or `rust-toolchain.toml` file giving the version,
+                           components and extra targets of the toolchain, instead of `version`,
+                           `iso_date` and `rustfmt_version`. See `rust_toolchain_repository`.
+      components: Other components to download, among "cargo", "clippy", "llvm-tools-preview" and
+                  "rust-src". See `rust_toolchain_repository`.
     """
 
+    if not exec_triple:
+        fail("rust_repository_set requires an exec_triple")
+
     rust_toolchain_repository(
         name = name,
         exec_triple = exec_triple,
@@ -470,24 +1515,69 @@ def rust_repository_set(
         version = version,
         rustfmt_version = rustfmt_version,
         edition = edition,
+        urls = urls,
+        sha256s = sha256s,
+        strict_checksums = strict_checksums,
+        rust_toolchain_file = rust_toolchain_file,
+        components = components,
     )
 
     rust_toolchain_repository_proxy(
         name = name + "_toolchains",
         exec_triple = exec_triple,
         extra_target_triples = extra_target_triples,
+        version = version,
+        rust_toolchain_file = rust_toolchain_file,
         parent_workspace_name = name,
         toolchain_name_prefix = DEFAULT_TOOLCHAIN_NAME_PREFIX,
     )
 
-    all_toolchain_names = []
-    for target_triple in [exec_triple] + extra_target_triples:
-        all_toolchain_names.append("@{name}_toolchains//:{toolchain_name_prefix}_{triple}".format(
-            name = name,
-            toolchain_name_prefix = DEFAULT_TOOLCHAIN_NAME_PREFIX,
-            triple = target_triple,
-        ))
+    # The targets of a rust-toolchain file are only known once the proxy repository reads it, so
+    # all of its toolchains are registered, including the dummy cc toolchain of bare-metal targets.
+    if rust_toolchain_file:
+        all_toolchain_names = ["@{}_toolchains//:all".format(name)]
+    else:
+        all_toolchain_names = []
+        for target_triple in [exec_triple] + extra_target_triples:
+            all_toolchain_names.append("@{name}_toolchains//:{toolchain_name_prefix}_{triple}".format(
+                name = name,
+                toolchain_name_prefix = DEFAULT_TOOLCHAIN_NAME_PREFIX,
+                triple = target_triple,
+            ))
+        if BUILD_for_bare_metal_cc_toolchain([exec_triple] + extra_target_triples):
+            all_toolchain_names.append("@{}_toolchains//:dummy_cc_bare_metal_toolchain".format(name))
 
     # Register toolchains
     native.register_toolchains(*all_toolchain_names)
     native.register_toolchains("@io_bazel_rules_rust//rust/private/dummy_cc_toolchain:dummy_cc_wasm32_toolchain")
+
+def rust_local_repository_set(
+        name,
+        sysroot = None,
+        version = None,
+        target_triples = [],
+        edition = None):
+    """Declares and registers the toolchains of a Rust toolchain which is already installed.
+
+    See `rust_local_toolchain_repository` for how the toolchain is found.
+
+    Args:
+      name: The name of the generated repository
+      sysroot: The sysroot of the toolchain, or None for that of the rustc on the PATH.
+      version: The version the toolchain is expected to be, or None to accept any.
+      target_triples: The rust-style targets to declare toolchains for, or all installed targets.
+      edition: The rust edition to be used by default (2015 (default) or 2018)
+    """
+
+    rust_local_toolchain_repository(
+        name = name,
+        sysroot = sysroot,
+        version = version,
+        target_triples = target_triples,
+        toolchain_name_prefix = DEFAULT_TOOLCHAIN_NAME_PREFIX,
+        edition = edition,
+    )
+
+    # This includes the dummy cc toolchain of bare-metal targets, if any are installed.
+    native.register_toolchains("@{}//toolchains:all".format(name))
+    native.register_toolchains("@io_bazel_rules_rust//rust/private/dummy_cc_toolchain:dummy_cc_wasm32_toolchain")
@@ -16,10 +16,23 @@ load(
     "@io_bazel_rules_rust//rust:private/rust.bzl",
     _rust_benchmark = "rust_benchmark",
     _rust_binary = "rust_binary",
+    _rust_compile_fail_test = "rust_compile_fail_test",
     _rust_library = "rust_library",
     _rust_test = "rust_test",
     _rust_test_binary = "rust_test_binary",
 )
+load(
+    "@io_bazel_rules_rust//rust:private/debug.bzl",
+    _rust_debug = "rust_debug",
+)
+load(
+    "@io_bazel_rules_rust//rust:private/objcopy.bzl",
+    _rust_binary_objcopy = "rust_binary_objcopy",
+)
+load(
+    "@io_bazel_rules_rust//rust:private/reproducibility.bzl",
+    _rust_reproducibility_test = "rust_reproducibility_test",
+)
 load(
     "@io_bazel_rules_rust//rust:private/rustdoc.bzl",
     _rust_doc = "rust_doc",
@@ -44,8 +57,20 @@ rust_test_binary = _rust_test_binary
 rust_benchmark = _rust_benchmark
 """ See @io_bazel_rules_rust//rust:private/rust.bzl for a complete description. """
 
+rust_compile_fail_test = _rust_compile_fail_test
+""" See @io_bazel_rules_rust//rust:private/rust.bzl for a complete description. """
+
+rust_binary_objcopy = _rust_binary_objcopy
+""" See @io_bazel_rules_rust//rust:private/objcopy.bzl for a complete description. """
+
+rust_debug = _rust_debug
+""" See @io_bazel_rules_rust//rust:private/debug.bzl for a complete description. """
+
 rust_doc = _rust_doc
 """ See @io_bazel_rules_rust//rust:private/rustdoc.bzl for a complete description. """
 
 rust_doc_test = _rust_doc_test
 """ See @io_bazel_rules_rust//rust:private/rustdoc.bzl for a complete description. """
+
+rust_reproducibility_test = _rust_reproducibility_test
+""" See @io_bazel_rules_rust//rust:private/reproducibility.bzl for a complete description. """
@@ -2,28 +2,117 @@
 The rust_toolchain rule definition and implementation.
 """
 
+# The settings of the Cargo profile used for each compilation mode, besides `opt_level`, with the
+# values they accept, or None if they are checked separately.
+_PROFILE_SETTINGS = {
+    "debug_info": None,
+    "debug_assertions": ["true", "false"],
+    "overflow_checks": ["true", "false"],
+    "codegen_units": None,
+    "lto": ["false", "true", "fat", "thin", "off"],
+    "panic": ["unwind", "abort"],
+    "incremental": None,
+}
+
+# The value of each profile setting in Bazel's compilation modes, which those given to
+# rust_toolchain are merged over.
+_PROFILE_DEFAULTS = {
+    "opt_level": {"opt": "3", "dbg": "0", "fastbuild": "0"},
+    "debug_info": {"opt": "0", "dbg": "2", "fastbuild": "0"},
+    "debug_assertions": {"opt": "false", "dbg": "true", "fastbuild": "true"},
+    "overflow_checks": {"opt": "false", "dbg": "true", "fastbuild": "true"},
+    "codegen_units": {"opt": "16", "dbg": "16", "fastbuild": "16"},
+    "lto": {"opt": "false", "dbg": "false", "fastbuild": "false"},
+    "panic": {"opt": "unwind", "dbg": "unwind", "fastbuild": "unwind"},
+    "incremental": {"opt": "", "dbg": "", "fastbuild": ""},
+}
+
+def _check_profile_setting(setting, compilation_mode, value):
+    """Fails if `value` isn't valid for the profile setting `setting`."""
+    allowed = _PROFILE_SETTINGS[setting]
+    if allowed and value not in allowed:
+        fail("Invalid {} {} for compilation mode {}. Expected one of: {}".format(
+            setting,
+            repr(value),
+            compilation_mode,
+            ", ".join(allowed),
+        ))
+    if setting == "codegen_units" and (not value.isdigit() or int(value) == 0):
+        fail("Invalid codegen_units {} for compilation mode {}. Expected a positive number".format(
+            repr(value),
+            compilation_mode,
+        ))
+    if setting == "incremental" and value and not (value.startswith("/") or value[1:3] in (":/", ":\\")):
+        fail("Invalid incremental {} for compilation mode {}. Expected an absolute path, or \"\"".format(
+            repr(value),
+            compilation_mode,
+        ))
+
 def _rust_toolchain_impl(ctx):
+    profile_values = {}
+    for setting, defaults in _PROFILE_DEFAULTS.items():
+        values = dict(defaults)
+        values.update(getattr(ctx.attr, setting))
+        profile_values[setting] = values
+
+    # The defaults define every setting, so only compilation modes added by the user can be missing.
     compilation_mode_opts = {}
-    for k, v in ctx.attr.opt_level.items():
-        if not k in ctx.attr.debug_info:
-            fail("Compilation mode {} is not defined in debug_info but is defined opt_level".format(k))
-        compilation_mode_opts[k] = struct(debug_info = ctx.attr.debug_info[k], opt_level = v)
-    for k, v in ctx.attr.debug_info.items():
-        if not k in ctx.attr.opt_level:
-            fail("Compilation mode {} is not defined in opt_level but is defined debug_info".format(k))
+    for k, v in profile_values["opt_level"].items():
+        profile = {"opt_level": v}
+        for setting in _PROFILE_SETTINGS:
+            values = profile_values[setting]
+            if not k in values:
+                fail("Compilation mode {} is not defined in {} but is defined opt_level".format(k, setting))
+            _check_profile_setting(setting, k, values[k])
+            profile[setting] = values[k]
+        compilation_mode_opts[k] = struct(**profile)
+    for setting in _PROFILE_SETTINGS:
+        for k in profile_values[setting]:
+            if not k in profile_values["opt_level"]:
+                fail("Compilation mode {} is not defined in opt_level but is defined {}".format(k, setting))
+
+    if ctx.attr.target_triple and ctx.attr.target_json:
+        fail("Only one of target_triple and target_json may be set", "target_json")
+    target_json = ctx.file.target_json
+    if target_json:
+        # rustc names a custom target after its specification file. Its architecture is declared
+        # here too, as the specification is only read when compiling, where it is checked against.
+        if not ctx.attr.target_arch:
+            fail("target_arch must be set to the `arch` of the target specification", "target_arch")
+        target_triple = target_json.basename[:-len(".json")]
+        target_flag_value = target_json.path
+        target_arch = ctx.attr.target_arch
+    else:
+        if ctx.attr.target_arch:
+            fail("target_arch can only be set with target_json", "target_arch")
+        target_triple = ctx.attr.target_triple
+        target_flag_value = target_triple
+        target_arch = target_triple.split("-")[0]
 
     toolchain = platform_common.ToolchainInfo(
         rustc = ctx.file.rustc,
         rust_doc = ctx.file.rust_doc,
         rustfmt = ctx.file.rustfmt,
+        rust_gdb = ctx.file.rust_gdb,
+        rust_lldb = ctx.file.rust_lldb,
+        rust_debugger_scripts = ctx.files.rust_debugger_scripts,
+        rust_lld = ctx.file.rust_lld,
+        llvm_objcopy = ctx.file.llvm_objcopy,
+        llvm_size = ctx.file.llvm_size,
+        llvm_tools = ctx.files.llvm_tools,
+        cargo = ctx.file.cargo,
+        clippy_driver = ctx.file.clippy_driver,
+        rust_src = ctx.attr.rust_src,
         rustc_lib = ctx.attr.rustc_lib,
         rust_lib = ctx.attr.rust_lib,
         staticlib_ext = ctx.attr.staticlib_ext,
         dylib_ext = ctx.attr.dylib_ext,
-        target_triple = ctx.attr.target_triple,
+        target_triple = target_triple,
+        target_json = target_json,
+        target_flag_value = target_flag_value,
         exec_triple = ctx.attr.exec_triple,
         os = ctx.attr.os,
-        target_arch = ctx.attr.target_triple.split("-")[0],
+        target_arch = target_arch,
         default_edition = ctx.attr.default_edition,
         compilation_mode_opts = compilation_mode_opts,
         crosstool_files = ctx.files._crosstool,
@@ -45,6 +134,52 @@ rust_toolchain = rule(
             doc = "The location of the `rustfmt` binary. Can be a direct source or a filegroup containing one item.",
             allow_single_file = True,
         ),
+        "rust_gdb": attr.label(
+            doc = "The location of the `rust-gdb` script, which runs gdb with the pretty printers for std types.",
+            allow_single_file = True,
+        ),
+        "rust_lldb": attr.label(
+            doc = "The location of the `rust-lldb` script, which runs lldb with the pretty printers for std types.",
+            allow_single_file = True,
+        ),
+        "rust_debugger_scripts": attr.label(
+            doc = "The pretty printers for std types in `lib/rustlib/etc`, loaded by `rust-gdb` and `rust-lldb`.",
+            allow_files = True,
+        ),
+        "rust_lld": attr.label(
+            doc = """
+The location of the `rust-lld` linker shipped with rustc. If set, binaries are linked by it instead
+of the linker of the C++ toolchain, as is done for bare-metal targets without a C toolchain.
+""",
+            allow_single_file = True,
+        ),
+        "llvm_objcopy": attr.label(
+            doc = "The location of `llvm-objcopy`, from the `llvm-tools-preview` component. Used by `rust_binary_objcopy`.",
+            allow_single_file = True,
+        ),
+        "llvm_size": attr.label(
+            doc = "The location of `llvm-size`, from the `llvm-tools-preview` component. Used by `rust_binary_objcopy`.",
+            allow_single_file = True,
+        ),
+        "llvm_tools": attr.label(
+            doc = "All the binaries of the `llvm-tools-preview` component, such as `llvm-profdata` and `llvm-cov`, for rules which run others than `llvm_objcopy` and `llvm_size`.",
+            allow_files = True,
+        ),
+        "cargo": attr.label(
+            doc = "The location of the `cargo` binary, from the `cargo` component, for rules which run Cargo.",
+            allow_single_file = True,
+        ),
+        "clippy_driver": attr.label(
+            doc = "The location of the `clippy-driver` binary, from the `clippy` component, which is run like rustc to lint crates.",
+            allow_single_file = True,
+        ),
+        "rust_src": attr.label(
+            doc = """
+The source of the standard library, from the `rust-src` component, as used by IDEs and to build the
+standard library from source.
+""",
+            allow_files = True,
+        ),
         "rustc_lib": attr.label(
             doc = "The libraries used by rustc during compilation.",
         ),
@@ -59,20 +194,57 @@ rust_toolchain = rule(
             default = "2015",
         ),
         "exec_triple": attr.string(),
-        "target_triple": attr.string(),
+        "target_triple": attr.string(
+            doc = "The triple of the platform the toolchain builds for, unless `target_json` is set.",
+        ),
+        "target_arch": attr.string(
+            doc = """
+The `arch` of the target specification in `target_json`, eg. "arm", which must be set with it.
+Compiling fails if it, or `os`, doesn't match the specification.
+""",
+        ),
+        "target_json": attr.label(
+            doc = """
+The specification of a custom target the toolchain builds for, instead of one of the targets built
+into rustc. The file name, without `.json`, is the name of the target.
+""",
+            allow_single_file = [".json"],
+        ),
         "_crosstool": attr.label(
             default = Label("@bazel_tools//tools/cpp:current_cc_toolchain"),
         ),
-        "opt_level": attr.string_dict(default = {
-            "opt": "3",
-            "dbg": "0",
-            "fastbuild": "0",
-        }),
-        "debug_info": attr.string_dict(default = {
-            "opt": "0",
-            "dbg": "2",
-            "fastbuild": "0",
-        }),
+        "opt_level": attr.string_dict(),
+        "debug_info": attr.string_dict(),
+        "debug_assertions": attr.string_dict(
+            doc = "Whether `debug_assert!` and similar checks are enabled in each compilation mode: \"true\" or \"false\".",
+        ),
+        "overflow_checks": attr.string_dict(
+            doc = "Whether integer overflow panics in each compilation mode: \"true\" or \"false\".",
+        ),
+        "codegen_units": attr.string_dict(
+            doc = "The number of code generation units each crate is split into in each compilation mode.",
+        ),
+        "lto": attr.string_dict(
+            doc = """
+The link time optimization of binaries and other final artifacts in each compilation mode, as in a
+Cargo profile: "false" for rustc's default of optimizing each crate separately, "true" or "fat" to
+optimize across all crates, "thin" for ThinLTO across all crates, or "off" for none at all.
+""",
+        ),
+        "panic": attr.string_dict(
+            doc = """
+The panic strategy of binaries and other final artifacts in each compilation mode: "unwind" or
+"abort". Libraries are built to unwind, so that they can be linked into either, and tests always
+unwind, as libtest catches their panics.
+""",
+        ),
+        "incremental": attr.string_dict(
+            doc = """
+The directory rustc keeps its incremental compilation state in for each compilation mode, or "" to
+compile from scratch. rustc reads and writes the directory outside of Bazel's knowledge, so it must
+be an absolute path, and compile actions are not sandboxed or cached remotely when it is set.
+""",
+        ),
     },
     doc = """
 Declares a Rust toolchain for use.
@@ -113,6 +285,44 @@ toolchain(
 Then, either add the label of the toolchain rule to `register_toolchains` in the WORKSPACE, or pass
 it to the `"--extra_toolchains"` flag for Bazel, and it will be used.
 
+Each of Bazel's compilation modes uses a profile like those of Cargo, which is made of the values
+for the mode in `opt_level`, `debug_info`, `debug_assertions`, `overflow_checks`, `codegen_units`,
+`lto`, `panic` and `incremental`. The values given for a mode replace its defaults, so only those
+which change need to be given, but a compilation mode other than `opt`, `dbg` and `fastbuild` must
+be defined in all of them. For example, to build release binaries with fat LTO which abort on panics:
+
+```python
+rust_toolchain(
+  name = "rust_cpuX_impl",
+  ...
+  lto = {"opt": "fat"},
+  panic = {"opt": "abort"},
+)
+```
+
+A target rustc doesn't know about, such as a custom embedded board, can be described by a
+[target specification](https://doc.rust-lang.org/rustc/targets/custom.html) given as `target_json`
+instead of `target_triple`. rustc reads the properties of the target from it, and they are exported
+to the crates as `CARGO_CFG_*` variables like those of built-in targets. Its `arch` and `os` must
+also be given as `target_arch` and `os`, which the rules need before rustc runs, and compiling fails
+if they don't match the specification. There is no prebuilt standard library for such targets, so
+`rust_lib` should be one built separately for the target:
+
+```python
+rust_toolchain(
+  name = "rust_myboard_impl",
+  rustc = "@rust_linux_x86_64//:rustc",
+  rustc_lib = "@rust_linux_x86_64//:rustc_lib",
+  rust_lib = "//third_party/myboard:core",
+  rust_doc = "@rust_linux_x86_64//:rustdoc",
+  staticlib_ext = ".a",
+  dylib_ext = ".so",
+  os = "none",
+  target_arch = "arm",
+  target_json = "//third_party/myboard:thumbv7em-myboard-none.json",
+)
+```
+
 See @io_bazel_rules_rust//rust:repositories.bzl for examples of defining the @rust_cpuX repository
 with the actual binaries and libraries.
 """,
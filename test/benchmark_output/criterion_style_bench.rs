@@ -0,0 +1,12 @@
+//! A benchmark which writes its estimates where criterion does, under `CRITERION_HOME`.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    let criterion_home = env::var_os("CRITERION_HOME").expect("CRITERION_HOME should be set");
+    let dir = PathBuf::from(criterion_home).join("noop").join("new");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("estimates.json"), "{\"mean\":{\"point_estimate\":1.0}}\n").unwrap();
+}
@@ -0,0 +1,22 @@
+#!/bin/bash
+
+# Runs a benchmark without the test protocol's TEST_UNDECLARED_OUTPUTS_DIR, as `bazel run` does,
+# and checks that the estimates.json it writes are in its default output_dir in the workspace.
+#
+# Usage: output_dir_test.sh <benchmark>
+
+set -euo pipefail
+
+benchmark="$1"
+workspace="$TEST_TMPDIR/workspace"
+mkdir -p "$workspace"
+
+env -u TEST_UNDECLARED_OUTPUTS_DIR -u XML_OUTPUT_FILE -u TEST_PREMATURE_EXIT_FILE \
+  BUILD_WORKSPACE_DIRECTORY="$workspace" "$benchmark"
+
+estimates="$workspace/bazel-benchmarks/test/benchmark_output/criterion_style_bench/criterion/noop/new/estimates.json"
+if [[ ! -f "$estimates" ]]; then
+  echo "Expected the benchmark to write $estimates, but the workspace contains:" >&2
+  find "$workspace" >&2
+  exit 1
+fi
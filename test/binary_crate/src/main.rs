@@ -0,0 +1,20 @@
+use std::env;
+
+fn greeting(name: &str) -> String {
+    format!("Hello, {}!", name)
+}
+
+fn main() {
+    let name = env::args().nth(1).unwrap_or_else(|| "world".to_owned());
+    println!("{}", greeting(&name));
+}
+
+#[cfg(test)]
+mod test {
+    use super::greeting;
+
+    #[test]
+    fn test_greeting() {
+        assert_eq!(greeting("Bazel"), "Hello, Bazel!");
+    }
+}
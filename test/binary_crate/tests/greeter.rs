@@ -0,0 +1,11 @@
+use std::process::Command;
+
+#[test]
+fn test_greeter_binary() {
+    let output = Command::new(env!("CARGO_BIN_EXE_greeter"))
+        .arg("Bazel")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "Hello, Bazel!\n");
+}
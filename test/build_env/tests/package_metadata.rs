@@ -0,0 +1,30 @@
+#[test]
+pub fn test_package_metadata() {
+    assert_eq!(env!("CARGO_PKG_NAME"), "package_metadata_test");
+    assert_eq!(
+        env!("CARGO_PKG_AUTHORS"),
+        "Alice <alice@example.com>:Bob <bob@example.com>"
+    );
+    assert_eq!(
+        env!("CARGO_PKG_DESCRIPTION"),
+        "Tests the Cargo package environment variables"
+    );
+    assert_eq!(
+        env!("CARGO_PKG_HOMEPAGE"),
+        "https://example.com/package_metadata"
+    );
+    assert_eq!(
+        env!("CARGO_PKG_REPOSITORY"),
+        "https://example.com/package_metadata.git"
+    );
+    assert_eq!(env!("CARGO_PKG_LICENSE"), "Apache-2.0");
+}
+
+#[test]
+pub fn test_package_version() {
+    assert_eq!(env!("CARGO_PKG_VERSION"), "1.2.3-alpha.1");
+    assert_eq!(env!("CARGO_PKG_VERSION_MAJOR"), "1");
+    assert_eq!(env!("CARGO_PKG_VERSION_MINOR"), "2");
+    assert_eq!(env!("CARGO_PKG_VERSION_PATCH"), "3");
+    assert_eq!(env!("CARGO_PKG_VERSION_PRE"), "alpha.1");
+}
@@ -0,0 +1,16 @@
+use std::env;
+use std::fs;
+
+#[test]
+pub fn test_runtime_env() {
+    let actual = env::var("USER_DEFINED_KEY").unwrap();
+    let expected = "USER_DEFINED_VALUE".to_owned();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+pub fn test_runtime_env_location() {
+    let actual = fs::read_to_string(env::var("DATA_FILE").unwrap()).unwrap();
+    let expected = "This file tests that CARGO_MANIFEST_DIR is set for the build environment\n";
+    assert_eq!(actual, expected);
+}
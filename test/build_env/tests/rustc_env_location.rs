@@ -0,0 +1,6 @@
+#[test]
+pub fn test_rustc_env_location() {
+    let actual = env!("DATA_FILE");
+    let expected = "test/build_env/src/manifest_dir_file.txt".to_owned();
+    assert_eq!(actual, expected);
+}
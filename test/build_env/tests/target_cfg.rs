@@ -0,0 +1,41 @@
+#[test]
+pub fn test_target_cfg_matches_rustc() {
+    // The environment describes the target the test is compiled for, so it agrees with the
+    // configuration rustc uses for `cfg!`.
+    assert_eq!(env!("CARGO_CFG_TARGET_ARCH"), std::env::consts::ARCH);
+    assert_eq!(env!("CARGO_CFG_TARGET_OS"), std::env::consts::OS);
+    assert_eq!(env!("CARGO_CFG_TARGET_FAMILY"), std::env::consts::FAMILY);
+    assert_eq!(
+        env!("CARGO_CFG_TARGET_ENDIAN"),
+        if cfg!(target_endian = "big") {
+            "big"
+        } else {
+            "little"
+        }
+    );
+    assert_eq!(
+        env!("CARGO_CFG_TARGET_POINTER_WIDTH"),
+        (std::mem::size_of::<usize>() * 8).to_string()
+    );
+}
+
+#[test]
+pub fn test_target_env_and_vendor() {
+    let env = env!("CARGO_CFG_TARGET_ENV");
+    assert_eq!(env == "gnu", cfg!(target_env = "gnu"));
+    assert_eq!(env == "musl", cfg!(target_env = "musl"));
+    assert_eq!(env == "msvc", cfg!(target_env = "msvc"));
+
+    let vendor = env!("CARGO_CFG_TARGET_VENDOR");
+    assert_eq!(vendor == "apple", cfg!(target_vendor = "apple"));
+    assert_eq!(vendor == "pc", cfg!(target_vendor = "pc"));
+}
+
+#[test]
+pub fn test_target_feature() {
+    let features: Vec<&str> = option_env!("CARGO_CFG_TARGET_FEATURE")
+        .unwrap_or("")
+        .split(',')
+        .collect();
+    assert_eq!(features.contains(&"sse2"), cfg!(target_feature = "sse2"));
+}
@@ -0,0 +1,3 @@
+use greeting_macro::greeting;
+
+greeting!();
@@ -0,0 +1,8 @@
+#![feature(test)]
+
+extern crate test;
+
+#[test]
+fn greets() {
+    assert_eq!(test::black_box(greeting::greeting()), "Hello");
+}
@@ -0,0 +1,4 @@
+#[test]
+fn greets() {
+    assert_eq!(greeting::greeting(), "Hello");
+}
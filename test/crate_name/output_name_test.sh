@@ -0,0 +1,17 @@
+#!/bin/bash
+
+# Checks that the name of each file matches the pattern before it.
+#
+# Usage: output_name_test.sh <pattern> <file> [<pattern> <file>...]
+
+set -euo pipefail
+
+while [[ $# -gt 0 ]]; do
+  pattern="$1"
+  file="$2"
+  shift 2
+  if [[ ! "$(basename "$file")" =~ ^${pattern}$ ]]; then
+    echo "Expected the name of $file to match $pattern" >&2
+    exit 1
+  fi
+done
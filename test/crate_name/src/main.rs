@@ -0,0 +1,5 @@
+extern crate greeter;
+
+fn main() {
+    println!("{}", greeter::greeting("world"));
+}
@@ -0,0 +1,6 @@
+extern crate greeter;
+
+#[test]
+fn test_greeting() {
+    assert_eq!(greeter::greeting("Bazel"), "Hello, Bazel!");
+}
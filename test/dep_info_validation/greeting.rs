@@ -0,0 +1,12 @@
+/// Returns the greeting in greeting.txt.
+pub fn greeting() -> &'static str {
+    include_str!("greeting.txt")
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn greets() {
+        assert_eq!(super::greeting(), "Hello");
+    }
+}
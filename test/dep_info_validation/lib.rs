@@ -0,0 +1,3 @@
+mod greeting;
+
+pub use greeting::greeting;
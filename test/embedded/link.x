@@ -0,0 +1,25 @@
+/* A minimal linker script for Cortex-M microcontrollers: the vector table holds the initial stack
+   pointer, at the end of RAM, and the reset handler. The memory layout is in memory.x. */
+INCLUDE memory.x
+
+ENTRY(reset);
+EXTERN(RESET_VECTOR);
+
+SECTIONS
+{
+  .vector_table ORIGIN(FLASH) :
+  {
+    LONG(ORIGIN(RAM) + LENGTH(RAM));
+    KEEP(*(.vector_table.reset_vector));
+  } > FLASH
+
+  .text :
+  {
+    *(.text .text.*);
+  } > FLASH
+
+  /DISCARD/ :
+  {
+    *(.ARM.exidx .ARM.exidx.*);
+  }
+}
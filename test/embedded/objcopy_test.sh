@@ -0,0 +1,29 @@
+#!/bin/bash
+
+# Checks the images of the firmware: the vector table at the start of the binary image begins with
+# the initial stack pointer, at the end of RAM, and the size report lists the vector table.
+#
+# Usage: objcopy_test.sh <.bin> <.hex> <.size.txt>
+
+set -euo pipefail
+
+bin="$1"
+hex="$2"
+size_report="$3"
+
+stack_pointer="$(od -A n -t x1 -N 4 "$bin" | tr -d ' ')"
+if [[ "$stack_pointer" != "00000120" ]]; then
+  echo "Expected $bin to start with the stack pointer 0x20010000, got $stack_pointer" >&2
+  exit 1
+fi
+
+if [[ "$(head -c 1 "$hex")" != ":" ]]; then
+  echo "Expected $hex to be an Intel HEX file" >&2
+  exit 1
+fi
+
+if ! grep -q '^\.vector_table ' "$size_report"; then
+  echo "Expected $size_report to list the .vector_table section:" >&2
+  cat "$size_report" >&2
+  exit 1
+fi
@@ -0,0 +1,19 @@
+extern crate test_env;
+
+use std::fs;
+use std::process;
+
+fn main() {
+    // The launcher must not treat this binary as a libtest binary, eg. by listing its tests.
+    if std::env::args().any(|arg| arg == "--list") {
+        eprintln!("Expected to be run without the libtest harness");
+        process::exit(1);
+    }
+
+    let tmpdir = test_env::test_tmpdir().expect("TEST_TMPDIR should be set");
+    let path = tmpdir.join("custom_main.txt");
+    fs::write(&path, "Written without libtest").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "Written without libtest");
+
+    println!("custom_main_test passed");
+}
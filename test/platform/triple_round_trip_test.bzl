@@ -0,0 +1,85 @@
+"""Tests that the platform of each supported triple maps back to the triple."""
+
+load("//rust/platform:platform.bzl", "SUPPORTED_PLATFORM_TRIPLES")
+load("//rust/platform:triple_mappings.bzl", "triple_to_constraint_set")
+
+def _platform_transition_impl(settings, attr):
+    return {"//command_line_option:platforms": str(attr.platform)}
+
+_platform_transition = transition(
+    implementation = _platform_transition_impl,
+    inputs = [],
+    outputs = ["//command_line_option:platforms"],
+)
+
+def _triple_round_trip_test_impl(ctx):
+    triple = ctx.attr.triple
+    constraint_set = triple_to_constraint_set(triple)
+    failures = []
+
+    for other in SUPPORTED_PLATFORM_TRIPLES:
+        if other != triple and sorted(triple_to_constraint_set(other)) == sorted(constraint_set):
+            failures.append("{} has the same constraints as {}".format(other, triple))
+
+    # Triples with the default ABI of a CPU and system have fewer constraints than the others, so
+    # their config_settings also match the platforms of the others. Those are more specific, so
+    # `select` still prefers the config_setting of the platform's own triple.
+    if triple not in ctx.attr.matched_triples:
+        failures.append("The platform of {} doesn't match its config_setting".format(triple))
+    for other in ctx.attr.matched_triples:
+        other_constraints = triple_to_constraint_set(other)
+        if other != triple and not (
+            len(other_constraints) < len(constraint_set) and
+            [c for c in other_constraints if c not in constraint_set] == []
+        ):
+            failures.append("The platform of {} matches the config_setting of {}".format(triple, other))
+
+    ctx.actions.write(
+        output = ctx.outputs.executable,
+        content = "\n".join(
+            ["#!/usr/bin/env bash"] +
+            ["echo 'FAIL: {}'".format(failure) for failure in failures] +
+            ["exit {}".format(1 if failures else 0)],
+        ),
+        is_executable = True,
+    )
+
+_triple_round_trip_test = rule(
+    implementation = _triple_round_trip_test_impl,
+    attrs = {
+        "triple": attr.string(mandatory = True),
+        "platform": attr.label(mandatory = True),
+        # The triples whose config_settings match `platform`.
+        "matched_triples": attr.string_list(),
+        "_whitelist_function_transition": attr.label(
+            default = "//tools/whitelists/function_transition_whitelist",
+        ),
+    },
+    cfg = _platform_transition,
+    test = True,
+)
+
+def triple_round_trip_test_suite(name):
+    """Declares a test for the platform of each supported triple, and a test_suite of them all."""
+    tests = []
+    for triple in SUPPORTED_PLATFORM_TRIPLES:
+        matched_triples = []
+        for other in SUPPORTED_PLATFORM_TRIPLES:
+            matched_triples += select({
+                "//rust/platform:" + other: [other],
+                "//conditions:default": [],
+            })
+
+        test_name = "{}_{}".format(name, triple)
+        _triple_round_trip_test(
+            name = test_name,
+            triple = triple,
+            platform = "//rust/platform/triples:" + triple,
+            matched_triples = matched_triples,
+        )
+        tests.append(test_name)
+
+    native.test_suite(
+        name = name,
+        tests = tests,
+    )
@@ -0,0 +1,60 @@
+"""Unit tests for remap_path."""
+
+load("@bazel_skylib//lib:unittest.bzl", "asserts", "unittest")
+load(
+    "//rust:private/rustc.bzl",
+    "REDACTED_BIN_DIR",
+    "REDACTED_EXTERNAL",
+    "remap_path",
+)
+
+def _external_test_impl(ctx):
+    env = unittest.begin(ctx)
+    asserts.equals(
+        env,
+        REDACTED_EXTERNAL + "/libc/src/lib.rs",
+        remap_path(ctx, "external/libc/src/lib.rs"),
+    )
+    return unittest.end(env)
+
+_external_test = unittest.make(_external_test_impl)
+
+def _bin_dir_test_impl(ctx):
+    env = unittest.begin(ctx)
+    asserts.equals(
+        env,
+        REDACTED_BIN_DIR + "/test/out.rs",
+        remap_path(ctx, ctx.bin_dir.path + "/test/out.rs"),
+    )
+    asserts.equals(
+        env,
+        REDACTED_BIN_DIR + "/external/libc/out.rs",
+        remap_path(ctx, ctx.bin_dir.path + "/external/libc/out.rs"),
+    )
+    return unittest.end(env)
+
+_bin_dir_test = unittest.make(_bin_dir_test_impl)
+
+def _sibling_directories_test_impl(ctx):
+    env = unittest.begin(ctx)
+    asserts.equals(env, "externals/lib.rs", remap_path(ctx, "externals/lib.rs"))
+    asserts.equals(env, ctx.bin_dir.path + "2/lib.rs", remap_path(ctx, ctx.bin_dir.path + "2/lib.rs"))
+    asserts.equals(env, "test/lib.rs", remap_path(ctx, "test/lib.rs"))
+    return unittest.end(env)
+
+_sibling_directories_test = unittest.make(_sibling_directories_test_impl)
+
+def remap_path_test_suite(name):
+    """Declares the tests of remap_path, and a test_suite of them all."""
+    tests = {
+        "external": _external_test,
+        "bin_dir": _bin_dir_test,
+        "sibling_directories": _sibling_directories_test,
+    }
+    for test_name, test in tests.items():
+        test(name = "{}_{}".format(name, test_name))
+
+    native.test_suite(
+        name = name,
+        tests = ["{}_{}".format(name, test_name) for test_name in tests],
+    )
@@ -0,0 +1,43 @@
+#!/bin/bash
+
+# Runs a rust_debug target with a fake gdb, which records its arguments, from a workspace whose
+# path has a space in it. Checks that rust-gdb loads the pretty printers of the toolchain, and that
+# the source paths rustc redacted are mapped back to the workspace.
+#
+# Usage: rust_debug_test.sh <rust_debug target>
+
+set -euo pipefail
+
+debug="$1"
+workspace="$TEST_TMPDIR/my workspace"
+args="$TEST_TMPDIR/gdb_args"
+mkdir -p "$workspace"
+
+fake_gdb="$TEST_TMPDIR/gdb"
+cat >"$fake_gdb" <<EOT
+#!/bin/bash
+printf '%s\n' "\$@" >"$args"
+EOT
+chmod +x "$fake_gdb"
+
+BUILD_WORKSPACE_DIRECTORY="$workspace" RUST_GDB="$fake_gdb" "$debug" --some-flag
+
+fail() {
+  echo "$1. gdb was run with:" >&2
+  cat "$args" >&2
+  exit 1
+}
+
+pretty_printers="$(sed -n 's/^--directory=//p' "$args")"
+if [[ ! -f "$pretty_printers/gdb_load_rust_pretty_printers.py" ]]; then
+  fail "Expected the pretty printers of the toolchain to be loaded"
+fi
+
+grep -qxF "set substitute-path \"__bazel_redacted_pwd\" \"$workspace\"" "$args" \
+  || fail "Expected the redacted execution root to be mapped to the workspace"
+
+if [[ "$(tail -n 3 "$args" | head -n 1)" != "--args" ]] \
+  || [[ "$(tail -n 2 "$args" | head -n 1)" != */test/rust_debug/hello ]] \
+  || [[ "$(tail -n 1 "$args")" != "--some-flag" ]]; then
+  fail "Expected gdb to run test/rust_debug/hello with the arguments of the wrapper"
+fi
@@ -0,0 +1,87 @@
+"""Unit tests for manifest_checksums."""
+
+load("@bazel_skylib//lib:unittest.bzl", "asserts", "unittest")
+load("//rust:repositories.bzl", "manifest_checksums")
+
+# Excerpts of channel-rust-1.39.0.toml and channel-rust-nightly.toml.
+_STABLE_MANIFEST = """
+manifest-version = "2"
+date = "2019-11-07"
+[pkg.rustc]
+version = "1.39.0 (4560ea788 2019-11-04)"
+[pkg.rustc.target.x86_64-unknown-linux-gnu]
+available = true
+url = "https://static.rust-lang.org/dist/2019-11-07/rustc-1.39.0-x86_64-unknown-linux-gnu.tar.gz"
+hash = "1111111111111111111111111111111111111111111111111111111111111111"
+xz_url = "https://static.rust-lang.org/dist/2019-11-07/rustc-1.39.0-x86_64-unknown-linux-gnu.tar.xz"
+xz_hash = "2222222222222222222222222222222222222222222222222222222222222222"
+
+[pkg.rustc.target.x86_64-unknown-linux-musl]
+available = false
+
+[pkg.rust-src]
+version = "1.39.0 (4560ea788 2019-11-04)"
+[pkg.rust-src.target."*"]
+available = true
+url = "https://static.rust-lang.org/dist/2019-11-07/rust-src-1.39.0.tar.gz"
+hash = "3333333333333333333333333333333333333333333333333333333333333333"
+
+[[pkg.rust.target.x86_64-unknown-linux-gnu.components]]
+pkg = "rustc"
+target = "x86_64-unknown-linux-gnu"
+
+[renames.clippy]
+to = "clippy-preview"
+"""
+
+_NIGHTLY_MANIFEST = """
+manifest-version = "2"
+date = "2020-04-19"
+[pkg.rust-std]
+version = "1.44.0-nightly (52fa23add 2020-04-18)"
+[pkg.rust-std.target.wasm32-unknown-unknown]
+available = true
+url = "https://static.rust-lang.org/dist/2020-04-19/rust-std-nightly-wasm32-unknown-unknown.tar.gz"
+hash = "4444444444444444444444444444444444444444444444444444444444444444"
+"""
+
+def _stable_test_impl(ctx):
+    env = unittest.begin(ctx)
+    asserts.equals(
+        env,
+        {
+            "rust-src-1.39.0": "3333333333333333333333333333333333333333333333333333333333333333",
+            "rustc-1.39.0-x86_64-unknown-linux-gnu": "1111111111111111111111111111111111111111111111111111111111111111",
+        },
+        manifest_checksums(_STABLE_MANIFEST),
+    )
+    return unittest.end(env)
+
+_stable_test = unittest.make(_stable_test_impl)
+
+def _nightly_test_impl(ctx):
+    env = unittest.begin(ctx)
+    asserts.equals(
+        env,
+        {
+            "2020-04-19/rust-std-nightly-wasm32-unknown-unknown": "4444444444444444444444444444444444444444444444444444444444444444",
+        },
+        manifest_checksums(_NIGHTLY_MANIFEST),
+    )
+    return unittest.end(env)
+
+_nightly_test = unittest.make(_nightly_test_impl)
+
+def manifest_checksums_test_suite(name):
+    """Declares the tests of manifest_checksums, and a test_suite of them all."""
+    tests = {
+        "stable": _stable_test,
+        "nightly": _nightly_test,
+    }
+    for test_name, test in tests.items():
+        test(name = "{}_{}".format(name, test_name))
+
+    native.test_suite(
+        name = name,
+        tests = ["{}_{}".format(name, test_name) for test_name in tests],
+    )
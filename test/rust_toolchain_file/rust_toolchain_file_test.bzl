@@ -0,0 +1,102 @@
+"""Unit tests for parse_rust_toolchain_file."""
+
+load("@bazel_skylib//lib:unittest.bzl", "asserts", "unittest")
+load("//rust:repositories.bzl", "parse_rust_toolchain_file")
+
+def _legacy_format_test_impl(ctx):
+    env = unittest.begin(ctx)
+    rust_toolchain = parse_rust_toolchain_file("nightly-2020-04-19\n")
+    asserts.equals(env, "nightly-2020-04-19", rust_toolchain.channel)
+    asserts.equals(env, [], rust_toolchain.components)
+    asserts.equals(env, [], rust_toolchain.targets)
+    asserts.equals(env, None, rust_toolchain.path)
+    return unittest.end(env)
+
+_legacy_format_test = unittest.make(_legacy_format_test_impl)
+
+def _toml_format_test_impl(ctx):
+    env = unittest.begin(ctx)
+    rust_toolchain = parse_rust_toolchain_file("""
+[toolchain]
+channel = "1.39.0"
+components = ["rustfmt", "clippy"]
+targets = ['wasm32-unknown-unknown']
+""")
+    asserts.equals(env, "1.39.0", rust_toolchain.channel)
+    asserts.equals(env, ["rustfmt", "clippy"], rust_toolchain.components)
+    asserts.equals(env, ["wasm32-unknown-unknown"], rust_toolchain.targets)
+    asserts.equals(env, None, rust_toolchain.path)
+    return unittest.end(env)
+
+_toml_format_test = unittest.make(_toml_format_test_impl)
+
+def _multi_line_arrays_test_impl(ctx):
+    env = unittest.begin(ctx)
+    rust_toolchain = parse_rust_toolchain_file("""
+[toolchain]
+channel = "nightly-2020-04-19"
+components = [
+    "rustfmt",
+    "rust-src",
+]
+targets = [
+    "wasm32-unknown-unknown",
+    "thumbv7em-none-eabihf"
+]
+""")
+    asserts.equals(env, "nightly-2020-04-19", rust_toolchain.channel)
+    asserts.equals(env, ["rustfmt", "rust-src"], rust_toolchain.components)
+    asserts.equals(env, ["wasm32-unknown-unknown", "thumbv7em-none-eabihf"], rust_toolchain.targets)
+    return unittest.end(env)
+
+_multi_line_arrays_test = unittest.make(_multi_line_arrays_test_impl)
+
+def _comments_test_impl(ctx):
+    env = unittest.begin(ctx)
+    rust_toolchain = parse_rust_toolchain_file("""
+# The toolchain of the project.
+[other]
+channel = "beta"
+
+[toolchain]  # Read by rustup.
+channel = "1.39.0"  # The minimum supported version.
+components = [
+    "rustfmt",  # For the format checks.
+    # "clippy",
+]
+""")
+    asserts.equals(env, "1.39.0", rust_toolchain.channel)
+    asserts.equals(env, ["rustfmt"], rust_toolchain.components)
+    asserts.equals(env, [], rust_toolchain.targets)
+    return unittest.end(env)
+
+_comments_test = unittest.make(_comments_test_impl)
+
+def _path_test_impl(ctx):
+    env = unittest.begin(ctx)
+    rust_toolchain = parse_rust_toolchain_file("""
+[toolchain]
+path = "/opt/rust"
+""")
+    asserts.equals(env, "/opt/rust", rust_toolchain.path)
+    asserts.equals(env, None, rust_toolchain.channel)
+    return unittest.end(env)
+
+_path_test = unittest.make(_path_test_impl)
+
+def rust_toolchain_file_test_suite(name):
+    """Declares the tests of parse_rust_toolchain_file, and a test_suite of them all."""
+    tests = {
+        "legacy_format": _legacy_format_test,
+        "toml_format": _toml_format_test,
+        "multi_line_arrays": _multi_line_arrays_test,
+        "comments": _comments_test,
+        "path": _path_test,
+    }
+    for test_name, test in tests.items():
+        test(name = "{}_{}".format(name, test_name))
+
+    native.test_suite(
+        name = name,
+        tests = ["{}_{}".format(name, test_name) for test_name in tests],
+    )
@@ -0,0 +1,32 @@
+use std::env;
+
+fn assert_sharded() {
+    let total: usize = env::var("TEST_TOTAL_SHARDS").unwrap().parse().unwrap();
+    let index: usize = env::var("TEST_SHARD_INDEX").unwrap().parse().unwrap();
+    assert!(index < total);
+}
+
+#[test]
+pub fn test_one() {
+    assert_sharded();
+}
+
+#[test]
+pub fn test_two() {
+    assert_sharded();
+}
+
+#[test]
+pub fn test_three() {
+    assert_sharded();
+}
+
+#[test]
+pub fn test_four() {
+    assert_sharded();
+}
+
+#[test]
+pub fn test_five() {
+    assert_sharded();
+}
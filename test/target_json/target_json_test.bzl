@@ -0,0 +1,42 @@
+"""Tests that crates built for a custom target are compiled with its specification."""
+
+_CUSTOM_TARGET_SETTINGS = {
+    "//command_line_option:platforms": "@io_bazel_rules_rust//test/target_json:myboard",
+    "//command_line_option:extra_toolchains": "@io_bazel_rules_rust//test/target_json:myboard_toolchain",
+}
+
+def _target_json_test_impl(ctx):
+    spec = ctx.file.target_json
+    failures = []
+
+    actions = [a for a in ctx.attr.target_under_test.actions if a.mnemonic == "Rustc"]
+    if len(actions) != 1:
+        failures.append("Expected one Rustc action, found {}".format(len(actions)))
+    for action in actions:
+        if "--target=" + spec.path not in action.argv:
+            failures.append("Expected rustc to be passed --target={}, got: {}".format(
+                spec.path,
+                " ".join(action.argv),
+            ))
+        if spec not in action.inputs.to_list():
+            failures.append("Expected {} to be an input of the Rustc action".format(spec.short_path))
+
+    return [AnalysisTestResultInfo(
+        success = not failures,
+        message = "\n".join(failures),
+    )]
+
+target_json_test = rule(
+    implementation = _target_json_test_impl,
+    attrs = {
+        "target_under_test": attr.label(
+            cfg = analysis_test_transition(settings = _CUSTOM_TARGET_SETTINGS),
+            mandatory = True,
+        ),
+        "target_json": attr.label(
+            allow_single_file = [".json"],
+            mandatory = True,
+        ),
+    },
+    analysis_test = True,
+)
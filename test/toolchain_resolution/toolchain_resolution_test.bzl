@@ -0,0 +1,76 @@
+"""Tests which Rust toolchain is resolved for a platform."""
+
+load("//rust:private/utils.bzl", "find_toolchain")
+load("//rust/platform:triple_mappings.bzl", "triple_to_toolchain_target_constraint_set")
+
+_ResolvedToolchainInfo = provider(fields = ["target_triple"])
+
+def _resolved_toolchain_impl(ctx):
+    return [_ResolvedToolchainInfo(target_triple = find_toolchain(ctx).target_triple)]
+
+# Only resolves the Rust toolchain, so no other toolchain is needed for the platforms tested.
+resolved_toolchain = rule(
+    implementation = _resolved_toolchain_impl,
+    toolchains = ["@io_bazel_rules_rust//rust:toolchain"],
+)
+
+def rust_test_toolchain(name, target_triple, exec_triple):
+    """Declares a toolchain for the rust_toolchain `<name>_impl`, as `rust_repository_set` would.
+
+    It runs on any platform, so that it can be resolved wherever the tests are run.
+    """
+    native.toolchain(
+        name = name,
+        target_compatible_with = triple_to_toolchain_target_constraint_set(target_triple, exec_triple),
+        toolchain = ":{}_impl".format(name),
+        toolchain_type = "//rust:toolchain",
+    )
+
+def _toolchain_resolution_test_impl(ctx):
+    target_triple = ctx.attr.target_under_test[_ResolvedToolchainInfo].target_triple
+    return [AnalysisTestResultInfo(
+        success = target_triple == ctx.attr.expected_target_triple,
+        message = "Expected the toolchain for {} to be resolved, got the one for {}".format(
+            ctx.attr.expected_target_triple,
+            target_triple,
+        ),
+    )]
+
+def _toolchain_resolution_test(platform, toolchains):
+    return rule(
+        implementation = _toolchain_resolution_test_impl,
+        attrs = {
+            "target_under_test": attr.label(
+                cfg = analysis_test_transition(settings = {
+                    "//command_line_option:platforms": platform,
+                    "//command_line_option:extra_toolchains": toolchains,
+                }),
+                mandatory = True,
+                providers = [_ResolvedToolchainInfo],
+            ),
+            "expected_target_triple": attr.string(mandatory = True),
+        },
+        analysis_test = True,
+    )
+
+# A windows-gnu toolchain builds for a Windows host, whose platform has no ABI.
+windows_host_gnu_test = _toolchain_resolution_test(
+    platform = "@io_bazel_rules_rust//test/toolchain_resolution:windows_host",
+    toolchains = ["@io_bazel_rules_rust//test/toolchain_resolution:x86_64-pc-windows-gnu"],
+)
+
+# It is still resolved for the windows-gnu platform, which has the GNU ABI.
+windows_gnu_platform_test = _toolchain_resolution_test(
+    platform = "@io_bazel_rules_rust//rust/platform/triples:x86_64-pc-windows-gnu",
+    toolchains = ["@io_bazel_rules_rust//test/toolchain_resolution:x86_64-pc-windows-gnu"],
+)
+
+# A toolchain which builds for windows-gnu from another triple keeps the GNU ABI, so that it isn't
+# used for MSVC, even when it is registered first.
+windows_msvc_platform_test = _toolchain_resolution_test(
+    platform = "@io_bazel_rules_rust//rust/platform/triples:x86_64-pc-windows-msvc",
+    toolchains = [
+        "@io_bazel_rules_rust//test/toolchain_resolution:x86_64-unknown-linux-gnu_x86_64-pc-windows-gnu",
+        "@io_bazel_rules_rust//test/toolchain_resolution:x86_64-pc-windows-msvc",
+    ],
+)
@@ -0,0 +1,191 @@
+//! JUnit XML reports, in the format Bazel reads from `XML_OUTPUT_FILE`.
+
+use std::io;
+use std::io::Write;
+use std::time::Duration;
+
+/// The result of a single test case.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed { message: String },
+    Skipped,
+}
+
+/// A single test case, eg. a `#[test]` function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestCase {
+    /// The full path of the test, eg. `parser::test_empty_input`.
+    pub name: String,
+    pub outcome: Outcome,
+    pub time: Duration,
+    /// Output captured while running the test.
+    pub stdout: String,
+}
+
+/// A collection of test cases run by one test binary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestSuite {
+    /// The name of the suite, conventionally the label of the test target.
+    pub name: String,
+    pub time: Duration,
+    pub cases: Vec<TestCase>,
+}
+
+impl TestSuite {
+    /// Writes the suite as a JUnit XML document.
+    pub fn write_xml<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let failures = self
+            .cases
+            .iter()
+            .filter(|case| match case.outcome {
+                Outcome::Failed { .. } => true,
+                _ => false,
+            })
+            .count();
+        let skipped = self
+            .cases
+            .iter()
+            .filter(|case| case.outcome == Outcome::Skipped)
+            .count();
+
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(w, "<testsuites>")?;
+        writeln!(
+            w,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="0" skipped="{}" time="{}">"#,
+            escape(&self.name),
+            self.cases.len(),
+            failures,
+            skipped,
+            seconds(self.time),
+        )?;
+        for case in &self.cases {
+            // Tests are grouped by module, which is how most dashboards present them.
+            let (classname, name) = match case.name.rfind("::") {
+                Some(i) => (&case.name[..i], &case.name[i + 2..]),
+                None => (&self.name[..], &case.name[..]),
+            };
+            write!(
+                w,
+                r#"    <testcase name="{}" classname="{}" time="{}""#,
+                escape(name),
+                escape(classname),
+                seconds(case.time),
+            )?;
+            if case.outcome == Outcome::Passed && case.stdout.is_empty() {
+                writeln!(w, "/>")?;
+                continue;
+            }
+            writeln!(w, ">")?;
+            match case.outcome {
+                Outcome::Passed => {}
+                Outcome::Failed { ref message } => writeln!(
+                    w,
+                    r#"      <failure message="{}" type="failure">{}</failure>"#,
+                    escape(message),
+                    escape(&case.stdout),
+                )?,
+                Outcome::Skipped => writeln!(w, "      <skipped/>")?,
+            }
+            if !case.stdout.is_empty() {
+                writeln!(w, "      <system-out>{}</system-out>", escape(&case.stdout))?;
+            }
+            writeln!(w, "    </testcase>")?;
+        }
+        writeln!(w, "  </testsuite>")?;
+        writeln!(w, "</testsuites>")
+    }
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{}.{:03}", duration.as_secs(), duration.subsec_millis())
+}
+
+/// Escapes text for use in XML attributes and character data.
+///
+/// Control characters aren't allowed in XML 1.0 documents at all, so they're dropped. Test output
+/// commonly contains them in the form of ANSI color codes, which are removed entirely.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{1b}' if chars.peek() == Some(&'[') => {
+                // Skip the parameters up to and including the final byte of the sequence.
+                chars.next();
+                while let Some(c) = chars.next() {
+                    if c >= '@' && c <= '~' {
+                        break;
+                    }
+                }
+            }
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' | '\r' | '\t' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_xml() {
+        let suite = TestSuite {
+            name: "//pkg:target".to_owned(),
+            time: Duration::from_millis(1500),
+            cases: vec![
+                TestCase {
+                    name: "parser::test_ok".to_owned(),
+                    outcome: Outcome::Passed,
+                    time: Duration::from_millis(2),
+                    stdout: String::new(),
+                },
+                TestCase {
+                    name: "test_bad".to_owned(),
+                    outcome: Outcome::Failed {
+                        message: "assertion failed: 1 < 0".to_owned(),
+                    },
+                    time: Duration::from_millis(30),
+                    stdout: "\u{1b}[31mthread 'test_bad' panicked\n".to_owned(),
+                },
+                TestCase {
+                    name: "parser::test_slow".to_owned(),
+                    outcome: Outcome::Skipped,
+                    time: Duration::from_millis(0),
+                    stdout: String::new(),
+                },
+            ],
+        };
+
+        let mut xml = Vec::new();
+        suite.write_xml(&mut xml).unwrap();
+        assert_eq!(
+            String::from_utf8(xml).unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="//pkg:target" tests="3" failures="1" errors="0" skipped="1" time="1.500">
+    <testcase name="test_ok" classname="parser" time="0.002"/>
+    <testcase name="test_bad" classname="//pkg:target" time="0.030">
+      <failure message="assertion failed: 1 &lt; 0" type="failure">thread &apos;test_bad&apos; panicked
+</failure>
+      <system-out>thread &apos;test_bad&apos; panicked
+</system-out>
+    </testcase>
+    <testcase name="test_slow" classname="parser" time="0.000">
+      <skipped/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! Runs a libtest binary under Bazel's test protocol.
+//!
+//! `rust_test` and `rust_benchmark` wrap their binaries with this launcher, which is invoked as:
+//!
+//! ```text
+//! launcher [--no_libtest_harness] [--output_dir=<dir>] <test binary> [test args...]
+//! ```
+//!
+//! When the target is sharded or filtered with `--test_filter`, the tests are listed with `--list`
+//! and only the selected ones are run. Toolchains before Rust 1.52 only accept one name filter, so
+//! the other tests are skipped with `--skip` instead, or the binary is run once per selected test.
+//!
+//! When Bazel asks for an `XML_OUTPUT_FILE`, the output of the test binary is parsed into a JUnit
+//! XML report. libtest's JSON events are used if the toolchain supports them, and its human
+//! readable output otherwise.
+//!
+//! Binaries built without the libtest harness (`use_libtest_harness = False`) are run as they are,
+//! as they are responsible for the test protocol themselves.
+//!
+//! `bazel run` provides no `TEST_UNDECLARED_OUTPUTS_DIR`, so with `--output_dir`, the files a
+//! benchmark writes there are kept in `<dir>` instead, relative to the root of the workspace.
+
+extern crate test_env;
+
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::time::Instant;
+
+use test_env::junit::TestSuite;
+use test_env::libtest::{JsonEventParser, OutputParser, PrettyParser};
+use test_env::{PrematureExitGuard, Shard, TestFilter, MAX_SELECTION_ARGS_LEN};
+
+/// Arguments asking libtest for a stream of JSON events, which is only supported on nightly.
+const JSON_EVENT_ARGS: &[&str] = &["-Z", "unstable-options", "--format=json", "--report-time"];
+
+/// Lists the names of the tests in a libtest binary.
+///
+/// Benchmarks are listed instead of tests if `--bench` is in `test_args`, as that's what the test
+/// binary would run.
+fn list_tests(test_binary: &OsString, test_args: &[OsString]) -> io::Result<Vec<String>> {
+    let output = Command::new(test_binary)
+        .args(test_args)
+        .arg("--list")
+        .arg("--format=terse")
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "Failed to list tests: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        ));
+    }
+
+    // Each test is printed as "<name>: test", and each benchmark as "<name>: bench" (or
+    // "<name>: benchmark", depending on the toolchain version).
+    let kinds: &[&str] = if test_args.iter().any(|arg| arg == "--bench") {
+        &[": bench", ": benchmark"]
+    } else {
+        &[": test"]
+    };
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            kinds
+                .iter()
+                .find(|kind| line.ends_with(*kind))
+                .map(|kind| line[..line.len() - kind.len()].to_owned())
+        })
+        .collect())
+}
+
+/// Returns whether the test binary runs the tests matching each of several name filters, rather
+/// than only those matching the first, by listing two of the `selected` tests.
+fn supports_multiple_filters(
+    test_binary: &OsString,
+    test_args: &[OsString],
+    selected: &[String],
+) -> io::Result<bool> {
+    if selected.len() < 2 {
+        return Ok(true);
+    }
+    let mut args = test_args.to_vec();
+    args.push("--exact".into());
+    args.extend(selected[..2].iter().map(OsString::from));
+    Ok(list_tests(test_binary, &args)?.len() == 2)
+}
+
+fn run(test_binary: &OsString, test_args: &[OsString]) -> io::Result<i32> {
+    // The arguments selecting the tests of each run of the test binary.
+    let mut runs = vec![Vec::new()];
+
+    let filter = TestFilter::from_env();
+    let shard = Shard::from_env()?;
+    if shard.is_some() {
+        test_env::acknowledge_sharding()?;
+    }
+    if filter.is_some() || shard.is_some() {
+        let all = list_tests(test_binary, test_args)?;
+        let mut selected = all.clone();
+        if let Some(ref filter) = filter {
+            selected.retain(|name| filter.matches(name));
+        }
+        if let Some(shard) = shard {
+            selected = shard.partition(&selected);
+        }
+        if selected.is_empty() {
+            match shard {
+                Some(shard) => println!(
+                    "No tests selected by the test filter in shard {} of {}",
+                    shard.index + 1,
+                    shard.total
+                ),
+                None => println!("No tests selected by the test filter"),
+            }
+            return Ok(0);
+        }
+        let multiple_filters = supports_multiple_filters(test_binary, test_args, &selected)?;
+        runs = test_env::selection_runs(&all, &selected, multiple_filters, MAX_SELECTION_ARGS_LEN);
+    }
+
+    let commands = runs
+        .into_iter()
+        .map(|run| {
+            let mut command = Command::new(test_binary);
+            command.args(test_args).args(run);
+            command
+        })
+        .collect();
+    match test_env::xml_output_file() {
+        Some(xml_output_file) => run_with_report(commands, test_binary, &xml_output_file),
+        None => {
+            let mut code = 0;
+            for mut command in commands {
+                // A test killed by a signal has no exit code; report it as a failure.
+                let status = command.status()?.code().unwrap_or(1);
+                if code == 0 {
+                    code = status;
+                }
+            }
+            Ok(code)
+        }
+    }
+}
+
+/// Runs a binary which provides its own `main` rather than using libtest.
+fn run_without_harness(test_binary: &OsString, test_args: &[OsString]) -> io::Result<i32> {
+    let mut command = Command::new(test_binary);
+    command.args(test_args);
+
+    // criterion writes its reports, including the estimates.json of each benchmark, to
+    // CRITERION_HOME. Collect them as undeclared outputs so that they're kept after the run.
+    if env::var_os("CRITERION_HOME").is_none() {
+        if let Some(dir) = test_env::undeclared_outputs_dir() {
+            command.env("CRITERION_HOME", dir.join("criterion"));
+        }
+    }
+
+    Ok(command.status()?.code().unwrap_or(1))
+}
+
+/// Points `TEST_UNDECLARED_OUTPUTS_DIR` at `output_dir` in the workspace when the binary is run
+/// with `bazel run`, which doesn't provide one.
+fn set_up_output_dir(output_dir: &OsString) -> io::Result<()> {
+    if test_env::undeclared_outputs_dir().is_some() {
+        return Ok(());
+    }
+    let workspace = match env::var_os("BUILD_WORKSPACE_DIRECTORY") {
+        Some(workspace) => PathBuf::from(workspace),
+        None => return Ok(()),
+    };
+    let dir = workspace.join(output_dir);
+    fs::create_dir_all(&dir)?;
+    println!("Writing the outputs of the benchmarks to {}", dir.display());
+    env::set_var("TEST_UNDECLARED_OUTPUTS_DIR", dir);
+    Ok(())
+}
+
+fn supports_json_events(test_binary: &OsString) -> bool {
+    Command::new(test_binary)
+        .args(JSON_EVENT_ARGS)
+        .arg("--list")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// Runs the test commands in turn, echoing their output while collecting their results into a
+/// JUnit XML report.
+fn run_with_report(
+    commands: Vec<Command>,
+    test_binary: &OsString,
+    xml_output_file: &Path,
+) -> io::Result<i32> {
+    let json_events = supports_json_events(test_binary);
+    let mut parser: Box<dyn OutputParser> = if json_events {
+        Box::new(JsonEventParser::new())
+    } else {
+        Box::new(PrettyParser::new())
+    };
+
+    let start = Instant::now();
+    let mut code = 0;
+    for mut command in commands {
+        if json_events {
+            command.args(JSON_EVENT_ARGS);
+        }
+        let mut child = command.stdout(Stdio::piped()).spawn()?;
+        {
+            let mut stdout = BufReader::new(child.stdout.take().expect("stdout should be piped"));
+            let mut buf = Vec::new();
+            while stdout.read_until(b'\n', &mut buf)? > 0 {
+                let line = String::from_utf8_lossy(&buf);
+                if let Some(text) = parser.parse_line(
+                    line.trim_end_matches(|c| c == '\n' || c == '\r'),
+                    start.elapsed(),
+                ) {
+                    println!("{}", text);
+                }
+                buf.clear();
+            }
+        }
+        // A test killed by a signal has no exit code; report it as a failure.
+        let status = child.wait()?.code().unwrap_or(1);
+        if code == 0 {
+            code = status;
+        }
+    }
+
+    let suite = TestSuite {
+        name: env::var("TEST_TARGET")
+            .unwrap_or_else(|_| test_binary.to_string_lossy().into_owned()),
+        time: start.elapsed(),
+        cases: parser.finish(start.elapsed()),
+    };
+    suite.write_xml(&mut BufWriter::new(File::create(xml_output_file)?))?;
+
+    Ok(code)
+}
+
+fn main() {
+    let mut args = env::args_os().skip(1).peekable();
+    let use_libtest_harness = match args.peek() {
+        Some(arg) if arg == "--no_libtest_harness" => false,
+        _ => true,
+    };
+    if !use_libtest_harness {
+        args.next();
+    }
+    let output_dir = match args.peek().and_then(|arg| arg.to_str()) {
+        Some(arg) if arg.starts_with("--output_dir=") => {
+            Some(OsString::from(&arg["--output_dir=".len()..]))
+        }
+        _ => None,
+    };
+    if output_dir.is_some() {
+        args.next();
+    }
+    let test_binary = args.next().expect(
+        "Usage: launcher [--no_libtest_harness] [--output_dir=<dir>] <test binary> [test args...]",
+    );
+    let test_args: Vec<OsString> = args.collect();
+
+    if let Some(ref output_dir) = output_dir {
+        set_up_output_dir(output_dir).expect("Failed to create the output directory");
+    }
+
+    let exit_guard = PrematureExitGuard::arm().expect("Failed to create premature exit file");
+    let result = if use_libtest_harness {
+        run(&test_binary, &test_args)
+    } else {
+        run_without_harness(&test_binary, &test_args)
+    };
+    let code = match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Failed to run {:?}: {}", test_binary, e);
+            1
+        }
+    };
+    exit_guard
+        .disarm()
+        .expect("Failed to remove premature exit file");
+    process::exit(code);
+}
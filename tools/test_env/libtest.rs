@@ -0,0 +1,521 @@
+//! Parsers for the output of libtest test binaries.
+//!
+//! Stable toolchains only offer libtest's human readable ("pretty") output, while nightly
+//! toolchains can report a stream of JSON events with `-Z unstable-options --format=json`.
+
+use std::collections::BTreeMap;
+use std::mem;
+use std::time::Duration;
+
+use junit::{Outcome, TestCase};
+
+/// Collects test cases from the lines a test binary prints to stdout.
+pub trait OutputParser {
+    /// Parses one line of output, printed `elapsed` after the test binary was started.
+    ///
+    /// Returns the text to show in the test log in place of the line, if any.
+    fn parse_line(&mut self, line: &str, elapsed: Duration) -> Option<String>;
+
+    /// Returns the test cases seen so far, `elapsed` after the test binary was started.
+    fn finish(&mut self, elapsed: Duration) -> Vec<TestCase>;
+}
+
+/// Extracts a one line summary of why a test failed from its captured output.
+fn failure_message(stdout: &str) -> String {
+    let mut lines = stdout.lines();
+    while let Some(line) = lines.next() {
+        if !line.contains("panicked at") {
+            continue;
+        }
+        // Newer toolchains print the panic location and message on separate lines.
+        if line.ends_with(':') {
+            if let Some(message) = lines.next() {
+                return format!("{} {}", line, message);
+            }
+        }
+        return line.to_owned();
+    }
+    "Test failed".to_owned()
+}
+
+/// Parses libtest's default human readable output.
+///
+/// This output doesn't include timing information, so each test is assumed to have taken the time
+/// since the previous result was printed. This is only accurate for tests run on a single thread.
+#[derive(Default)]
+pub struct PrettyParser {
+    cases: Vec<TestCase>,
+    last_result: Duration,
+    /// The index of the case whose captured output is currently being printed.
+    capturing: Option<usize>,
+}
+
+impl PrettyParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl OutputParser for PrettyParser {
+    fn parse_line(&mut self, line: &str, elapsed: Duration) -> Option<String> {
+        if line.starts_with("---- ") && line.ends_with(" stdout ----") {
+            let name = &line["---- ".len()..line.len() - " stdout ----".len()];
+            self.capturing = self.cases.iter().position(|case| case.name == name);
+        } else if line == "failures:" || line == "successes:" || line.starts_with("test result: ") {
+            self.capturing = None;
+        } else if let Some(i) = self.capturing {
+            let stdout = &mut self.cases[i].stdout;
+            stdout.push_str(line);
+            stdout.push('\n');
+        } else if line.starts_with("test ") && line.contains(" ... ") {
+            let mut parts = line["test ".len()..].splitn(2, " ... ");
+            let name = parts.next().unwrap_or_default();
+            let result = parts.next().unwrap_or_default();
+            let outcome = match result.split_whitespace().next() {
+                Some("ok") => Outcome::Passed,
+                Some("FAILED") => Outcome::Failed {
+                    message: String::new(),
+                },
+                Some("ignored") | Some("ignored,") => Outcome::Skipped,
+                // Benchmarks and anything else we don't understand aren't test cases.
+                _ => return Some(line.to_owned()),
+            };
+            let time = if elapsed > self.last_result {
+                elapsed - self.last_result
+            } else {
+                Duration::from_secs(0)
+            };
+            self.last_result = elapsed;
+            self.cases.push(TestCase {
+                name: name.to_owned(),
+                outcome,
+                time,
+                stdout: String::new(),
+            });
+        }
+        Some(line.to_owned())
+    }
+
+    fn finish(&mut self, _elapsed: Duration) -> Vec<TestCase> {
+        let mut cases = mem::replace(&mut self.cases, Vec::new());
+        for case in &mut cases {
+            // The captured output is followed by blank lines separating it from the next section.
+            let len = case.stdout.trim_end().len();
+            case.stdout.truncate(len);
+            if !case.stdout.is_empty() {
+                case.stdout.push('\n');
+            }
+            if let Outcome::Failed { ref mut message } = case.outcome {
+                *message = failure_message(&case.stdout);
+            }
+        }
+        cases
+    }
+}
+
+/// Parses the JSON events printed by libtest with `--format=json`.
+#[derive(Default)]
+pub struct JsonEventParser {
+    cases: Vec<TestCase>,
+    /// The tests which have started but not yet finished, with the time at which they started.
+    running: BTreeMap<String, Duration>,
+}
+
+impl JsonEventParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn finish_test(&mut self, event: &BTreeMap<String, Value>, elapsed: Duration) -> String {
+        let name = event.get("name").and_then(Value::as_str).unwrap_or("");
+        let started = self.running.remove(name).unwrap_or(elapsed);
+        let time = event
+            .get("exec_time")
+            .and_then(Value::as_duration)
+            .unwrap_or_else(|| elapsed - started);
+        let stdout = event
+            .get("stdout")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_owned();
+
+        let (outcome, summary) = match event.get("event").and_then(Value::as_str) {
+            Some("ok") => (Outcome::Passed, "ok"),
+            Some("ignored") => (Outcome::Skipped, "ignored"),
+            _ => {
+                // libtest reports eg. a `should_panic` test which didn't panic in `message`.
+                let message = match event.get("message").and_then(Value::as_str) {
+                    Some(message) => message.to_owned(),
+                    None => failure_message(&stdout),
+                };
+                (Outcome::Failed { message }, "FAILED")
+            }
+        };
+
+        let mut text = format!("test {} ... {}", name, summary);
+        if let Outcome::Failed { .. } = outcome {
+            text.push_str(&format!("\n---- {} stdout ----\n{}", name, stdout));
+        }
+        self.cases.push(TestCase {
+            name: name.to_owned(),
+            outcome,
+            time,
+            stdout,
+        });
+        text
+    }
+}
+
+impl OutputParser for JsonEventParser {
+    fn parse_line(&mut self, line: &str, elapsed: Duration) -> Option<String> {
+        // Tests run with `--nocapture` interleave their own output with the events.
+        let event = match parse_flat_object(line) {
+            Some(event) => event,
+            None => return Some(line.to_owned()),
+        };
+        let field = |key: &str| event.get(key).and_then(Value::as_str).unwrap_or("");
+        match (field("type"), field("event")) {
+            ("suite", "started") => Some(format!(
+                "running {} tests",
+                event
+                    .get("test_count")
+                    .and_then(Value::as_number)
+                    .unwrap_or(0.0)
+            )),
+            ("suite", result) => Some(format!(
+                "test result: {}. {} passed; {} failed; {} ignored",
+                result,
+                event
+                    .get("passed")
+                    .and_then(Value::as_number)
+                    .unwrap_or(0.0),
+                event
+                    .get("failed")
+                    .and_then(Value::as_number)
+                    .unwrap_or(0.0),
+                event
+                    .get("ignored")
+                    .and_then(Value::as_number)
+                    .unwrap_or(0.0),
+            )),
+            ("test", "started") => {
+                self.running.insert(field("name").to_owned(), elapsed);
+                None
+            }
+            // Emitted for tests which run for longer than expected, before their result.
+            ("test", "timeout") => None,
+            ("test", _) => Some(self.finish_test(&event, elapsed)),
+            _ => Some(line.to_owned()),
+        }
+    }
+
+    fn finish(&mut self, elapsed: Duration) -> Vec<TestCase> {
+        // Tests which never finished were most likely running when the test binary crashed.
+        for (name, started) in mem::replace(&mut self.running, BTreeMap::new()) {
+            self.cases.push(TestCase {
+                name,
+                outcome: Outcome::Failed {
+                    message: "Test did not complete".to_owned(),
+                },
+                time: elapsed - started,
+                stdout: String::new(),
+            });
+        }
+        mem::replace(&mut self.cases, Vec::new())
+    }
+}
+
+/// A JSON value, as used in libtest events.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    String(String),
+    Number(f64),
+    Literal(String),
+}
+
+impl Value {
+    fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match *self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Durations are reported as a number of seconds, or as a string like "0.003s" by older
+    /// toolchains.
+    fn as_duration(&self) -> Option<Duration> {
+        let seconds = match *self {
+            Value::Number(n) => n,
+            Value::String(ref s) => s.trim_end_matches('s').parse().ok()?,
+            _ => return None,
+        };
+        if seconds < 0.0 {
+            return None;
+        }
+        let nanos = (seconds.fract() * 1e9) as u32;
+        Some(Duration::new(seconds.trunc() as u64, nanos))
+    }
+}
+
+/// Parses a JSON object whose values are all strings, numbers or literals.
+///
+/// libtest's events are all of this form, so this avoids depending on a full JSON library.
+fn parse_flat_object(line: &str) -> Option<BTreeMap<String, Value>> {
+    let mut chars = line.trim().chars().peekable();
+    let mut object = BTreeMap::new();
+
+    if chars.next()? != '{' {
+        return None;
+    }
+    loop {
+        skip_whitespace(&mut chars);
+        match chars.next()? {
+            '}' if object.is_empty() => break,
+            '"' => {}
+            _ => return None,
+        }
+        let key = parse_string(&mut chars)?;
+        skip_whitespace(&mut chars);
+        if chars.next()? != ':' {
+            return None;
+        }
+        skip_whitespace(&mut chars);
+        let value = match *chars.peek()? {
+            '"' => {
+                chars.next();
+                Value::String(parse_string(&mut chars)?)
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_digit() || "+-.eE".contains(c)) {
+                        break;
+                    }
+                    number.push(c);
+                    chars.next();
+                }
+                Value::Number(number.parse().ok()?)
+            }
+            c if c.is_ascii_alphabetic() => {
+                let mut literal = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_alphabetic() {
+                        break;
+                    }
+                    literal.push(c);
+                    chars.next();
+                }
+                Value::Literal(literal)
+            }
+            _ => return None,
+        };
+        object.insert(key, value);
+        skip_whitespace(&mut chars);
+        match chars.next()? {
+            ',' => continue,
+            '}' => break,
+            _ => return None,
+        }
+    }
+
+    if chars.next().is_some() {
+        return None;
+    }
+    Some(object)
+}
+
+fn skip_whitespace<I: Iterator<Item = char>>(chars: &mut ::std::iter::Peekable<I>) {
+    while chars.peek().map_or(false, |c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+/// Parses the rest of a JSON string, after its opening quote.
+fn parse_string<I: Iterator<Item = char>>(chars: &mut I) -> Option<String> {
+    let mut s = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(s),
+            '\\' => match chars.next()? {
+                'n' => s.push('\n'),
+                'r' => s.push('\r'),
+                't' => s.push('\t'),
+                'b' => s.push('\u{8}'),
+                'f' => s.push('\u{c}'),
+                'u' => {
+                    let high = parse_hex4(chars)?;
+                    let code = if high >= 0xD800 && high < 0xDC00 {
+                        // A surrogate pair, encoded as two consecutive escapes.
+                        if chars.next()? != '\\' || chars.next()? != 'u' {
+                            return None;
+                        }
+                        let low = parse_hex4(chars)?;
+                        0x10000 + ((high - 0xD800) << 10) + (low.checked_sub(0xDC00)? & 0x3FF)
+                    } else {
+                        high
+                    };
+                    s.push(::std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                }
+                c => s.push(c),
+            },
+            c => s.push(c),
+        }
+    }
+}
+
+fn parse_hex4<I: Iterator<Item = char>>(chars: &mut I) -> Option<u32> {
+    let mut code = 0;
+    for _ in 0..4 {
+        code = code * 16 + chars.next()?.to_digit(16)?;
+    }
+    Some(code)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn feed<P: OutputParser>(parser: &mut P, lines: &[(&str, u64)]) -> Vec<Option<String>> {
+        lines
+            .iter()
+            .map(|&(line, millis)| parser.parse_line(line, Duration::from_millis(millis)))
+            .collect()
+    }
+
+    #[test]
+    fn test_pretty_output() {
+        let mut parser = PrettyParser::new();
+        feed(
+            &mut parser,
+            &[
+                ("", 0),
+                ("running 3 tests", 0),
+                ("test parser::test_ok ... ok", 10),
+                ("test parser::test_ignored ... ignored", 10),
+                ("test test_bad ... FAILED", 25),
+                ("", 25),
+                ("failures:", 25),
+                ("", 25),
+                ("---- test_bad stdout ----", 25),
+                ("thread 'test_bad' panicked at 'oh no', src/lib.rs:3:5", 25),
+                ("", 25),
+                ("", 25),
+                ("failures:", 25),
+                ("    test_bad", 25),
+                ("", 25),
+                ("test result: FAILED. 1 passed; 1 failed; 1 ignored", 25),
+            ],
+        );
+
+        assert_eq!(
+            parser.finish(Duration::from_millis(30)),
+            vec![
+                TestCase {
+                    name: "parser::test_ok".to_owned(),
+                    outcome: Outcome::Passed,
+                    time: Duration::from_millis(10),
+                    stdout: String::new(),
+                },
+                TestCase {
+                    name: "parser::test_ignored".to_owned(),
+                    outcome: Outcome::Skipped,
+                    time: Duration::from_millis(0),
+                    stdout: String::new(),
+                },
+                TestCase {
+                    name: "test_bad".to_owned(),
+                    outcome: Outcome::Failed {
+                        message: "thread 'test_bad' panicked at 'oh no', src/lib.rs:3:5".to_owned(),
+                    },
+                    time: Duration::from_millis(15),
+                    stdout: "thread 'test_bad' panicked at 'oh no', src/lib.rs:3:5\n".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_events() {
+        let mut parser = JsonEventParser::new();
+        let echoed = feed(
+            &mut parser,
+            &[
+                (
+                    r#"{ "type": "suite", "event": "started", "test_count": 3 }"#,
+                    0,
+                ),
+                (
+                    r#"{ "type": "test", "event": "started", "name": "a::ok" }"#,
+                    0,
+                ),
+                (
+                    r#"{ "type": "test", "event": "started", "name": "bad" }"#,
+                    0,
+                ),
+                (
+                    r#"{ "type": "test", "event": "started", "name": "hangs" }"#,
+                    0,
+                ),
+                (
+                    r#"{ "type": "test", "name": "a::ok", "event": "ok", "exec_time": 0.25 }"#,
+                    300,
+                ),
+                (
+                    r#"{ "type": "test", "name": "bad", "event": "failed", "stdout": "thread 'bad' panicked at src/lib.rs:1:1:\nboom é\n" }"#,
+                    400,
+                ),
+            ],
+        );
+        assert_eq!(echoed[0], Some("running 3 tests".to_owned()));
+        assert_eq!(echoed[1], None);
+        assert_eq!(echoed[4], Some("test a::ok ... ok".to_owned()));
+
+        assert_eq!(
+            parser.finish(Duration::from_millis(500)),
+            vec![
+                TestCase {
+                    name: "a::ok".to_owned(),
+                    outcome: Outcome::Passed,
+                    time: Duration::from_millis(250),
+                    stdout: String::new(),
+                },
+                TestCase {
+                    name: "bad".to_owned(),
+                    outcome: Outcome::Failed {
+                        message: "thread 'bad' panicked at src/lib.rs:1:1: boom \u{e9}".to_owned(),
+                    },
+                    time: Duration::from_millis(400),
+                    stdout: "thread 'bad' panicked at src/lib.rs:1:1:\nboom \u{e9}\n".to_owned(),
+                },
+                TestCase {
+                    name: "hangs".to_owned(),
+                    outcome: Outcome::Failed {
+                        message: "Test did not complete".to_owned(),
+                    },
+                    time: Duration::from_millis(500),
+                    stdout: String::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_flat_object() {
+        let object = parse_flat_object(r#"{"a": "x\"y", "b": -1.5e1, "c": true}"#).unwrap();
+        assert_eq!(object["a"], Value::String("x\"y".to_owned()));
+        assert_eq!(object["b"], Value::Number(-15.0));
+        assert_eq!(object["c"], Value::Literal("true".to_owned()));
+
+        assert_eq!(parse_flat_object("{}"), Some(BTreeMap::new()));
+        assert_eq!(parse_flat_object("hello from a test"), None);
+        assert_eq!(parse_flat_object(r#"{"a": {"nested": 1}}"#), None);
+        assert_eq!(parse_flat_object(r#"{"a": 1} trailing"#), None);
+    }
+}
@@ -0,0 +1,390 @@
+//! Accessors for the environment Bazel sets up when running a test.
+//!
+//! See the [Test Encyclopedia](https://docs.bazel.build/versions/master/test-encyclopedia.html)
+//! for a description of the protocol implemented here.
+//!
+//! USAGE:
+//!
+//! 1.  Depend on this library from your test rule:
+//!     ```python
+//!       rust_test(
+//!           name = "my_test",
+//!           ...
+//!           deps = ["@io_bazel_rules_rust//tools/test_env"],
+//!       )
+//!     ```
+//!
+//! 2.  Use the accessors to find the directories Bazel provides to the test:
+//!     ```ignore -- These variables are only set when running under `bazel test`.
+//!     extern crate test_env;
+//!
+//!     let tmp = test_env::test_tmpdir().unwrap();
+//!     let outputs = test_env::undeclared_outputs_dir().unwrap();
+//!     std::fs::write(outputs.join("report.txt"), "...").unwrap();
+//!     ```
+
+use std::env;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::PathBuf;
+
+pub mod junit;
+pub mod libtest;
+
+/// Returns the path of an environment variable, if it is set to a non-empty value.
+fn env_path(key: &str) -> Option<PathBuf> {
+    env::var_os(key)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Returns the private writable directory of the test (`TEST_TMPDIR`).
+pub fn test_tmpdir() -> Option<PathBuf> {
+    env_path("TEST_TMPDIR")
+}
+
+/// Returns the directory in which files written by the test are collected by Bazel into
+/// `bazel-testlogs/<target>/test.outputs/outputs.zip` (`TEST_UNDECLARED_OUTPUTS_DIR`).
+pub fn undeclared_outputs_dir() -> Option<PathBuf> {
+    env_path("TEST_UNDECLARED_OUTPUTS_DIR")
+}
+
+/// The tests selected with `bazel test --test_filter=...` (`TESTBRIDGE_TEST_ONLY`).
+///
+/// The filter is a comma-separated list of patterns. A test is selected if its name contains any
+/// of the patterns, or if it is equal to a pattern prefixed with `=`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestFilter {
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Pattern {
+    Substring(String),
+    Exact(String),
+}
+
+impl TestFilter {
+    /// Reads the filter from the environment.
+    ///
+    /// Returns `None` if no filter was given.
+    pub fn from_env() -> Option<TestFilter> {
+        env::var("TESTBRIDGE_TEST_ONLY")
+            .ok()
+            .and_then(|filter| TestFilter::parse(&filter))
+    }
+
+    /// Parses a filter, returning `None` if it contains no patterns.
+    pub fn parse(filter: &str) -> Option<TestFilter> {
+        let patterns: Vec<Pattern> = filter
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(|pattern| {
+                if pattern.starts_with('=') {
+                    Pattern::Exact(pattern[1..].to_owned())
+                } else {
+                    Pattern::Substring(pattern.to_owned())
+                }
+            })
+            .collect();
+        if patterns.is_empty() {
+            None
+        } else {
+            Some(TestFilter { patterns })
+        }
+    }
+
+    /// Returns whether the test with the given name is selected by the filter.
+    pub fn matches(&self, name: &str) -> bool {
+        self.patterns.iter().any(|pattern| match *pattern {
+            Pattern::Substring(ref substring) => name.contains(&substring[..]),
+            Pattern::Exact(ref exact) => name == exact,
+        })
+    }
+}
+
+/// Returns the path at which Bazel expects a JUnit XML report of the test results
+/// (`XML_OUTPUT_FILE`).
+///
+/// If nothing is written there, Bazel generates a report with a single test case for the target.
+pub fn xml_output_file() -> Option<PathBuf> {
+    env_path("XML_OUTPUT_FILE")
+}
+
+/// The shard of a test target that the current process is expected to run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shard {
+    /// The zero-based index of this shard (`TEST_SHARD_INDEX`).
+    pub index: usize,
+    /// The number of shards the test target was split into (`TEST_TOTAL_SHARDS`).
+    pub total: usize,
+}
+
+impl Shard {
+    /// Reads the shard assignment from the environment.
+    ///
+    /// Returns `Ok(None)` if the test target isn't sharded.
+    pub fn from_env() -> io::Result<Option<Shard>> {
+        let total = match env::var("TEST_TOTAL_SHARDS") {
+            Ok(total) => parse_env_usize("TEST_TOTAL_SHARDS", &total)?,
+            Err(_) => return Ok(None),
+        };
+        if total <= 1 {
+            return Ok(None);
+        }
+        let index = match env::var("TEST_SHARD_INDEX") {
+            Ok(index) => parse_env_usize("TEST_SHARD_INDEX", &index)?,
+            Err(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "TEST_TOTAL_SHARDS is set but TEST_SHARD_INDEX is not",
+                ))
+            }
+        };
+        if index >= total {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "TEST_SHARD_INDEX ({}) must be less than TEST_TOTAL_SHARDS ({})",
+                    index, total
+                ),
+            ));
+        }
+        Ok(Some(Shard { index, total }))
+    }
+
+    /// Selects the test names that belong to this shard.
+    ///
+    /// The names are sorted before being dealt out round-robin, so that every shard computes the
+    /// same partition regardless of the order in which the test binary lists its tests.
+    pub fn partition<S: AsRef<str>>(&self, names: &[S]) -> Vec<String> {
+        let mut sorted: Vec<&str> = names.iter().map(|name| name.as_ref()).collect();
+        sorted.sort();
+        sorted.dedup();
+        sorted
+            .into_iter()
+            .enumerate()
+            .filter(|&(position, _)| position % self.total == self.index)
+            .map(|(_, name)| name.to_owned())
+            .collect()
+    }
+}
+
+/// The most bytes of test names passed to a single run of a test binary, which keeps its command
+/// line well below the limits of every platform (32 KiB on Windows).
+pub const MAX_SELECTION_ARGS_LEN: usize = 16 * 1024;
+
+/// Returns the arguments of each run of a libtest binary needed to run exactly the `selected`
+/// tests among `all` of its tests.
+///
+/// Toolchains since Rust 1.52 accept any number of name filters, so the selected names are passed
+/// as filters, split over as many runs as needed to stay under `max_len` bytes. Older toolchains
+/// only use the first filter, so the tests which aren't selected are skipped instead, or, if there
+/// are too many of them for one command line, each selected test is run on its own.
+pub fn selection_runs<S: AsRef<str>>(
+    all: &[S],
+    selected: &[S],
+    multiple_filters: bool,
+    max_len: usize,
+) -> Vec<Vec<String>> {
+    let exact = || vec!["--exact".to_owned()];
+    if multiple_filters {
+        let mut runs = Vec::new();
+        let mut run = exact();
+        let mut len = 0;
+        for name in selected {
+            let name = name.as_ref();
+            if len > 0 && len + name.len() + 1 > max_len {
+                runs.push(mem::replace(&mut run, exact()));
+                len = 0;
+            }
+            run.push(name.to_owned());
+            len += name.len() + 1;
+        }
+        runs.push(run);
+        return runs;
+    }
+
+    let selected: Vec<&str> = selected.iter().map(|name| name.as_ref()).collect();
+    let mut skipped: Vec<&str> = all
+        .iter()
+        .map(|name| name.as_ref())
+        .filter(|name| !selected.contains(name))
+        .collect();
+    skipped.sort();
+    skipped.dedup();
+    let skipped_len: usize = skipped.iter().map(|name| name.len() + "--skip".len() + 2).sum();
+    if skipped_len <= max_len {
+        let mut run = exact();
+        for name in skipped {
+            run.push("--skip".to_owned());
+            run.push(name.to_owned());
+        }
+        vec![run]
+    } else {
+        selected
+            .into_iter()
+            .map(|name| vec!["--exact".to_owned(), name.to_owned()])
+            .collect()
+    }
+}
+
+fn parse_env_usize(key: &str, value: &str) -> io::Result<usize> {
+    value.trim().parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a valid number: {:?}", key, value),
+        )
+    })
+}
+
+/// Tells Bazel that the test understands sharding by touching `TEST_SHARD_STATUS_FILE`.
+///
+/// Bazel fails sharded test targets that don't do this, as every shard would otherwise run the
+/// whole test suite.
+pub fn acknowledge_sharding() -> io::Result<()> {
+    match env_path("TEST_SHARD_STATUS_FILE") {
+        Some(path) => touch(&path),
+        None => Ok(()),
+    }
+}
+
+/// Marks the test as running until `disarm` is called.
+///
+/// The `TEST_PREMATURE_EXIT_FILE` is created when armed and removed when disarmed, so that Bazel
+/// can tell a test which exited early (eg. by calling `std::process::exit(0)` from a test case)
+/// apart from one which ran to completion.
+pub struct PrematureExitGuard {
+    path: Option<PathBuf>,
+}
+
+impl PrematureExitGuard {
+    /// Creates the `TEST_PREMATURE_EXIT_FILE`, if Bazel requested one.
+    pub fn arm() -> io::Result<Self> {
+        let path = env_path("TEST_PREMATURE_EXIT_FILE");
+        if let Some(ref path) = path {
+            touch(path)?;
+        }
+        Ok(PrematureExitGuard { path })
+    }
+
+    /// Removes the `TEST_PREMATURE_EXIT_FILE`, signalling that the test exited normally.
+    pub fn disarm(self) -> io::Result<()> {
+        match self.path {
+            Some(path) => match fs::remove_file(&path) {
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                result => result,
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+fn touch(path: &PathBuf) -> io::Result<()> {
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_partition_is_disjoint_and_complete() {
+        let all = names(&["c", "a", "e", "b", "d"]);
+        let mut seen = Vec::new();
+        for index in 0..3 {
+            seen.extend(Shard { index, total: 3 }.partition(&all));
+        }
+        seen.sort();
+        assert_eq!(seen, names(&["a", "b", "c", "d", "e"]));
+    }
+
+    #[test]
+    fn test_partition_ignores_listing_order() {
+        let shard = Shard { index: 1, total: 2 };
+        assert_eq!(
+            shard.partition(&["b", "d", "a", "c"]),
+            shard.partition(&["a", "b", "c", "d"]),
+        );
+        assert_eq!(shard.partition(&["b", "d", "a", "c"]), names(&["b", "d"]));
+    }
+
+    #[test]
+    fn test_partition_with_more_shards_than_tests() {
+        assert!(Shard { index: 4, total: 5 }.partition(&["a"]).is_empty());
+    }
+
+    #[test]
+    fn test_selection_with_multiple_filters() {
+        assert_eq!(
+            selection_runs(&["a", "bb", "c"], &["a", "bb"], true, 1024),
+            vec![names(&["--exact", "a", "bb"])],
+        );
+    }
+
+    #[test]
+    fn test_selection_with_multiple_filters_is_split_by_length() {
+        assert_eq!(
+            selection_runs(&["aaa", "bbb", "ccc", "d"], &["aaa", "bbb", "ccc"], true, 8),
+            vec![
+                names(&["--exact", "aaa", "bbb"]),
+                names(&["--exact", "ccc"]),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_selection_with_single_filter_skips_the_rest() {
+        assert_eq!(
+            selection_runs(&["c", "a", "b", "a"], &["b"], false, 1024),
+            vec![names(&["--exact", "--skip", "a", "--skip", "c"])],
+        );
+    }
+
+    #[test]
+    fn test_selection_with_single_filter_runs_each_test_when_too_many_are_skipped() {
+        assert_eq!(
+            selection_runs(&["a", "b", "c", "d"], &["a", "b"], false, 10),
+            vec![names(&["--exact", "a"]), names(&["--exact", "b"])],
+        );
+    }
+
+    #[test]
+    fn test_filter_substring() {
+        let filter = TestFilter::parse("parser::").unwrap();
+        assert!(filter.matches("parser::test_empty"));
+        assert!(filter.matches("lexer::parser::test_empty"));
+        assert!(!filter.matches("lexer::test_empty"));
+    }
+
+    #[test]
+    fn test_filter_exact() {
+        let filter = TestFilter::parse("=parser::test_empty").unwrap();
+        assert!(filter.matches("parser::test_empty"));
+        assert!(!filter.matches("parser::test_empty_input"));
+    }
+
+    #[test]
+    fn test_filter_multiple() {
+        let filter = TestFilter::parse("parser::, =lexer::test_empty,").unwrap();
+        assert!(filter.matches("parser::test_empty"));
+        assert!(filter.matches("lexer::test_empty"));
+        assert!(!filter.matches("lexer::test_empty_input"));
+    }
+
+    #[test]
+    fn test_filter_empty() {
+        assert_eq!(TestFilter::parse(""), None);
+        assert_eq!(TestFilter::parse(" , "), None);
+    }
+}
@@ -4,11 +4,15 @@
 # dumps the result to //rust:known_shas.bzl
 #
 # Should be run from workspace root.
+#
+# Without access to static.rust-lang.org, use the rust_toolchain_checksums repository rule in
+# //rust:repositories.bzl instead.
 
 export LC_ALL=C
 
 TOOLS="$(cat ./util/fetch_shas_TOOLS.txt)"
 TARGETS="$(cat ./util/fetch_shas_TARGETS.txt)"
+STDLIB_TARGETS="$(cat ./util/fetch_shas_STDLIB_TARGETS.txt)"
 VERSIONS="$(cat ./util/fetch_shas_VERSIONS.txt)"
 BETA_ISO_DATES="$(cat ./util/fetch_shas_BETA_ISO_DATES.txt)"
 NIGHTLY_ISO_DATES="$(cat ./util/fetch_shas_NIGHTLY_ISO_DATES.txt)"
@@ -38,6 +42,48 @@ enumerate_keys() {
   done
 }
 
+# rust-src is the same for all targets, so its archives have no target triple.
+enumerate_rust_src_keys() {
+  for VERSION in $VERSIONS
+  do
+    echo "rust-src-$VERSION"
+  done
+
+  for ISO_DATE in $BETA_ISO_DATES
+  do
+    echo "$ISO_DATE/rust-src-beta"
+  done
+
+  for ISO_DATE in $NIGHTLY_ISO_DATES
+  do
+    echo "$ISO_DATE/rust-src-nightly"
+  done
+}
+
+# Targets which only have a standard library, such as wasm32-unknown-unknown, which the default
+# toolchains build for.
+enumerate_stdlib_keys() {
+  for TARGET in $STDLIB_TARGETS
+  do
+    for VERSION in $VERSIONS
+    do
+      echo "rust-std-$VERSION-$TARGET"
+    done
+
+    for ISO_DATE in $BETA_ISO_DATES
+    do
+      echo "$ISO_DATE/rust-std-beta-$TARGET"
+    done
+
+    for ISO_DATE in $NIGHTLY_ISO_DATES
+    do
+      echo "$ISO_DATE/rust-std-nightly-$TARGET"
+    done
+  done
+}
+
+# Stable rustfmt archives are downloaded by their own version, without a date, and unstable ones
+# from their dated directory.
 enumerate_rustfmt_keys() {
     for RUSTFMT_TARGET in $RUSTFMT_TARGETS
     do
@@ -60,4 +106,4 @@ emit_bzl_file_contents() {
   echo "}"
 }
 
-echo "$(emit_bzl_file_contents $(enumerate_keys) $(enumerate_rustfmt_keys))" > ./rust/known_shas.bzl
+echo "$(emit_bzl_file_contents $(enumerate_keys) $(enumerate_stdlib_keys) $(enumerate_rust_src_keys) $(enumerate_rustfmt_keys))" > ./rust/known_shas.bzl